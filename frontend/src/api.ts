@@ -25,8 +25,13 @@ export async function createMarker(req: CreateMarkerRequest): Promise<CreateMark
   return response.json();
 }
 
-export async function getMarkers(): Promise<GetMarkersResponse> {
-  const response = await fetch(`${API_BASE}/api/markers`);
+export async function getMarkers(cursor?: string): Promise<GetMarkersResponse> {
+  const params = new URLSearchParams();
+  if (cursor) {
+    params.set('cursor', cursor);
+  }
+
+  const response = await fetch(`${API_BASE}/api/markers?${params}`);
 
   if (!response.ok) {
     const text = await response.text();
@@ -52,6 +57,11 @@ export async function getLog(afterId: number, limit: number = 100): Promise<GetL
   return response.json();
 }
 
+export function logStreamUrl(afterId: number): string {
+  const params = new URLSearchParams({ after_id: afterId.toString() });
+  return `${API_BASE}/api/log/stream?${params}`;
+}
+
 export async function getIcons(): Promise<GetIconsResponse> {
   const response = await fetch(`${API_BASE}/api/icons`);
 
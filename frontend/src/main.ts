@@ -42,7 +42,7 @@ async function init(): Promise<void> {
   await loadInitialMarkers();
 
   // Start polling for new markers
-  startPolling();
+  startLiveUpdates();
 
   // Start expiry checking
   startExpiryCheck();
@@ -130,14 +130,25 @@ async function handleGlobeClick(coords: {
 
 async function loadInitialMarkers(): Promise<void> {
   try {
-    const response = await api.getMarkers();
-    console.log(`Loaded ${response.markers.length} markers (last 24h)`);
-
-    for (const marker of response.markers) {
-      state.addMarker(appState, marker);
+    // Log polling resumes from the first page's max_id
+    let response = await api.getMarkers();
+    const maxId = response.max_id;
+    appState.windowMs = response.window_ms;
+
+    let count = 0;
+    for (;;) {
+      for (const marker of response.markers) {
+        state.addMarker(appState, marker);
+      }
+      count += response.markers.length;
+      if (!response.has_more || !response.next_cursor) {
+        break;
+      }
+      response = await api.getMarkers(response.next_cursor);
     }
+    console.log(`Loaded ${count} markers (last ${response.window_hours}h)`);
 
-    appState.lastId = response.max_id;
+    appState.lastId = maxId;
     state.saveLastId(appState.lastId);
 
     updateGlobeMarkers();
@@ -146,6 +157,32 @@ async function loadInitialMarkers(): Promise<void> {
   }
 }
 
+function startLiveUpdates(): void {
+  if (typeof EventSource === "undefined") {
+    startPolling();
+    return;
+  }
+
+  // The browser reconnects on its own, resuming via Last-Event-ID
+  const source = new EventSource(api.logStreamUrl(appState.lastId));
+
+  source.addEventListener("marker", (event) => {
+    const marker: Marker = JSON.parse((event as MessageEvent).data);
+    state.addMarker(appState, marker);
+
+    if (marker.id > appState.lastId) {
+      appState.lastId = marker.id;
+      state.saveLastId(appState.lastId);
+    }
+
+    updateGlobeMarkers();
+  });
+
+  source.onerror = () => {
+    console.warn("Log stream interrupted, reconnecting...");
+  };
+}
+
 function startPolling(): void {
   setInterval(async () => {
     try {
@@ -10,6 +10,7 @@ export function createState(): AppState {
     markersByUuid: new Map(),
     selectedIconId: null,
     icons: [],
+    windowMs: 24 * 60 * 60 * 1000,
   };
 }
 
@@ -39,7 +40,7 @@ export function addMarker(state: AppState, marker: Marker): void {
 }
 
 export function removeExpiredMarkers(state: AppState): UUID[] {
-  const cutoffMs = Date.now() - 24 * 60 * 60 * 1000;
+  const cutoffMs = Date.now() - state.windowMs;
   const removed: UUID[] = [];
 
   for (const [uuid, marker] of state.markersByUuid) {
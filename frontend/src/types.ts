@@ -33,8 +33,12 @@ export interface CreateMarkerResponse {
 // Response for GET /api/markers
 export interface GetMarkersResponse {
   window_hours: number;
+  window_ms: number;
   server_time_ms: EpochMs;
   max_id: number;
+  limit: number;
+  has_more: boolean;
+  next_cursor: string | null;
   markers: Marker[];
 }
 
@@ -42,6 +46,7 @@ export interface GetMarkersResponse {
 export interface GetMarkersAtResponse {
   at_epoch_ms: EpochMs;
   window_hours: number;
+  window_ms: number;
   markers: Marker[];
 }
 
@@ -51,6 +56,7 @@ export interface GetLogResponse {
   limit: number;
   server_time_ms: EpochMs;
   max_id: number;
+  min_id: number;
   has_more: boolean;
   entries: Marker[];
 }
@@ -73,4 +79,6 @@ export interface AppState {
   markersByUuid: Map<UUID, Marker>;
   selectedIconId: IconId | null;
   icons: Icon[];
+  // Visibility window reported by the server
+  windowMs: number;
 }
@@ -0,0 +1,27 @@
+-- Spatio-temporal index over markers (each one stored as a zero-size box in
+-- lat, lon and time), so a bbox query over a short window only visits markers
+-- from that window rather than the whole history of the area.
+-- R*Tree coordinates are 32-bit floats, rounded outwards when stored (about
+-- two minutes of slack for epoch ms), so queries use it as a prefilter and
+-- re-check the exact lat/lon/ts_epoch_ms in marker_log.
+CREATE VIRTUAL TABLE IF NOT EXISTS marker_log_rtree USING rtree(
+    id,
+    min_lat, max_lat,
+    min_lon, max_lon,
+    min_ts, max_ts
+);
+
+-- Keep the index in sync (marker_log is append-only, so inserts suffice)
+CREATE TRIGGER IF NOT EXISTS tr_marker_log_rtree_insert
+AFTER INSERT ON marker_log
+BEGIN
+    INSERT INTO marker_log_rtree (id, min_lat, max_lat, min_lon, max_lon, min_ts, max_ts)
+    VALUES (NEW.id, NEW.lat, NEW.lat, NEW.lon, NEW.lon, NEW.ts_epoch_ms, NEW.ts_epoch_ms);
+END;
+
+-- Backfill rows logged before the index existed (no-op once in sync).
+-- The %_rowid shadow table gives the highest indexed id without a scan.
+INSERT INTO marker_log_rtree (id, min_lat, max_lat, min_lon, max_lon, min_ts, max_ts)
+SELECT id, lat, lat, lon, lon, ts_epoch_ms, ts_epoch_ms
+FROM marker_log
+WHERE id > (SELECT COALESCE(MAX(rowid), 0) FROM marker_log_rtree_rowid);
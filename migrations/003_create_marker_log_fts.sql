@@ -0,0 +1,32 @@
+-- Full-text index over marker labels (rowid = marker_log.id)
+CREATE VIRTUAL TABLE IF NOT EXISTS marker_log_fts USING fts5(
+    label,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Labels are indexed with the control characters U+0001-U+0003 escaped as
+-- U+0001 followed by U+0001, U+0004 or U+0005, so U+0002/U+0003 in highlight()
+-- output only ever delimit matches. Control characters separate tokens, so the
+-- escapes tokenize like the originals.
+
+-- Keep the index in sync (marker_log is append-only, so inserts suffice)
+CREATE TRIGGER IF NOT EXISTS tr_marker_log_fts_insert
+AFTER INSERT ON marker_log
+WHEN NEW.label IS NOT NULL
+BEGIN
+    INSERT INTO marker_log_fts (rowid, label)
+    VALUES (
+        NEW.id,
+        replace(replace(replace(NEW.label,
+            char(1), char(1, 1)), char(2), char(1, 4)), char(3), char(1, 5))
+    );
+END;
+
+-- Backfill labels logged before the index existed (no-op once in sync)
+INSERT INTO marker_log_fts (rowid, label)
+SELECT id,
+    replace(replace(replace(label,
+        char(1), char(1, 1)), char(2), char(1, 4)), char(3), char(1, 5))
+FROM marker_log
+WHERE label IS NOT NULL
+  AND id > COALESCE((SELECT rowid FROM marker_log_fts ORDER BY rowid DESC LIMIT 1), 0);
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+
+use crate::models::Marker;
+
+/// Default number of entries buffered per subscriber before it is considered lagging.
+pub const DEFAULT_BUS_CAPACITY: usize = 1024;
+
+/// In-process bus of committed log entries.
+///
+/// Writers publish each marker after its insert has committed. Subscribers get
+/// every entry in publish order; a subscriber that falls more than the bus
+/// capacity behind receives `LogEvent::Lagged` and should resync from the
+/// database with `db::get_log_after`.
+#[derive(Clone)]
+pub struct ChangeBus {
+    entries: broadcast::Sender<Marker>,
+    latest_id: Arc<watch::Sender<i64>>,
+}
+
+/// Event received from a `LogSubscription`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogEvent {
+    /// A newly committed log entry.
+    Entry(Marker),
+    /// Entries were dropped because the subscriber fell behind. Resync with
+    /// `db::get_log_after(pool, after_id, ..)`; entries received afterwards may
+    /// overlap the resync and can be skipped by id.
+    Lagged { after_id: i64, skipped: u64 },
+}
+
+impl ChangeBus {
+    pub fn new(capacity: usize) -> Self {
+        let (entries, _) = broadcast::channel(capacity);
+        let (latest_id, _) = watch::channel(0);
+        Self {
+            entries,
+            latest_id: Arc::new(latest_id),
+        }
+    }
+
+    /// Publish a committed log entry to all subscribers.
+    pub fn publish(&self, marker: &Marker) {
+        self.latest_id.send_if_modified(|latest| {
+            if marker.id > *latest {
+                *latest = marker.id;
+                true
+            } else {
+                false
+            }
+        });
+        // No receivers is not an error; nobody is listening yet.
+        let _ = self.entries.send(marker.clone());
+    }
+
+    /// Subscribe to entries published from now on.
+    pub fn subscribe(&self) -> LogSubscription {
+        let rx = self.entries.subscribe();
+        let last_id = *self.latest_id.borrow();
+        LogSubscription { rx, last_id }
+    }
+
+    /// Watch the highest published log id. Cheaper than `subscribe` for readers
+    /// that only need a wake-up and fetch entries from the database themselves.
+    pub fn watch(&self) -> watch::Receiver<i64> {
+        self.latest_id.subscribe()
+    }
+}
+
+impl Default for ChangeBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_BUS_CAPACITY)
+    }
+}
+
+/// A subscriber's view of the change bus.
+pub struct LogSubscription {
+    rx: broadcast::Receiver<Marker>,
+    last_id: i64,
+}
+
+impl LogSubscription {
+    /// Wait for the next event. Returns None once the bus has been dropped.
+    pub async fn recv(&mut self) -> Option<LogEvent> {
+        match self.rx.recv().await {
+            Ok(marker) => {
+                self.last_id = self.last_id.max(marker.id);
+                Some(LogEvent::Entry(marker))
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => Some(LogEvent::Lagged {
+                after_id: self.last_id,
+                skipped,
+            }),
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+
+    /// Highest log id seen by this subscriber (or published before it subscribed).
+    pub fn last_id(&self) -> i64 {
+        self.last_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(id: i64) -> Marker {
+        Marker {
+            id,
+            uuid: format!("uuid-{}", id),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "marker".to_string(),
+            label: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_receive() {
+        let bus = ChangeBus::default();
+        let mut sub = bus.subscribe();
+
+        bus.publish(&marker(1));
+        bus.publish(&marker(2));
+
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(1))));
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(2))));
+        assert_eq!(sub.last_id(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_starts_after_published_entries() {
+        let bus = ChangeBus::default();
+        bus.publish(&marker(1));
+
+        let mut sub = bus.subscribe();
+        assert_eq!(sub.last_id(), 1);
+
+        bus.publish(&marker(2));
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(2))));
+    }
+
+    #[tokio::test]
+    async fn test_lagged_subscriber_gets_resync_point() {
+        let bus = ChangeBus::new(2);
+        let mut sub = bus.subscribe();
+
+        bus.publish(&marker(1));
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(1))));
+
+        for id in 2..=5 {
+            bus.publish(&marker(id));
+        }
+
+        assert_eq!(
+            sub.recv().await,
+            Some(LogEvent::Lagged {
+                after_id: 1,
+                skipped: 2
+            })
+        );
+        // The newest entries are still delivered after the lag notice
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(4))));
+        assert_eq!(sub.recv().await, Some(LogEvent::Entry(marker(5))));
+    }
+
+    #[tokio::test]
+    async fn test_watch_tracks_highest_id() {
+        let bus = ChangeBus::default();
+        let rx = bus.watch();
+
+        bus.publish(&marker(3));
+        bus.publish(&marker(2));
+
+        assert_eq!(*rx.borrow(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_recv_returns_none_when_bus_dropped() {
+        let bus = ChangeBus::default();
+        let mut sub = bus.subscribe();
+        drop(bus);
+
+        assert_eq!(sub.recv().await, None);
+    }
+}
@@ -1,15 +1,39 @@
 use std::net::SocketAddr;
 
+/// One hour in milliseconds.
+const HOUR_MS: i64 = 60 * 60 * 1000;
+
+/// Default visibility window for current markers (24 hours).
+pub const DEFAULT_WINDOW_MS: i64 = 24 * HOUR_MS;
+
+/// Default upper bound for a per-request `window_ms` (7 days).
+pub const DEFAULT_MAX_WINDOW_MS: i64 = 168 * HOUR_MS;
+
+/// Default cap on markers returned per `/api/markers` page.
+pub const DEFAULT_MAX_MARKERS_PAGE: i64 = 10_000;
+
 /// Server configuration from environment variables.
 #[derive(Debug, Clone)]
 pub struct Config {
     pub listen_addr: SocketAddr,
     pub database_url: String,
+    /// Visibility window used when a request does not give `window_ms`.
+    pub window_ms: i64,
+    /// Largest `window_ms` a request may ask for.
+    pub max_window_ms: i64,
+    /// Most markers returned in one `/api/markers` page.
+    pub max_markers_page: i64,
+    /// Public origin of the server, e.g. `https://fylge.example`, for
+    /// absolute links in exports.
+    pub public_url: Option<String>,
 }
 
 impl Config {
     /// Load configuration from environment variables.
     /// DATABASE_URL defaults to "sqlite://fylge.db"
+    /// WINDOW_HOURS defaults to 24, MAX_WINDOW_HOURS to 168
+    /// MAX_MARKERS_PAGE defaults to 10000
+    /// PUBLIC_URL is unset by default
     pub fn from_env() -> Result<Self, ConfigError> {
         let database_url =
             std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://fylge.db".to_string());
@@ -19,13 +43,66 @@ impl Config {
             .parse()
             .map_err(|_| ConfigError::Invalid("LISTEN_ADDR", "must be a valid socket address"))?;
 
+        let window_ms = hours_from_env("WINDOW_HOURS", DEFAULT_WINDOW_MS)?;
+        let max_window_ms = hours_from_env("MAX_WINDOW_HOURS", DEFAULT_MAX_WINDOW_MS)?;
+        if window_ms > max_window_ms {
+            return Err(ConfigError::Invalid(
+                "WINDOW_HOURS",
+                "must not exceed MAX_WINDOW_HOURS",
+            ));
+        }
+
+        let max_markers_page = match std::env::var("MAX_MARKERS_PAGE") {
+            Ok(value) => match value.parse::<u32>() {
+                Ok(n) if n > 0 => i64::from(n),
+                _ => {
+                    return Err(ConfigError::Invalid(
+                        "MAX_MARKERS_PAGE",
+                        "must be a positive integer",
+                    ))
+                }
+            },
+            Err(_) => DEFAULT_MAX_MARKERS_PAGE,
+        };
+
+        let public_url = match std::env::var("PUBLIC_URL") {
+            Ok(value) if value.starts_with("http://") || value.starts_with("https://") => {
+                Some(value.trim_end_matches('/').to_string())
+            }
+            Ok(_) => {
+                return Err(ConfigError::Invalid(
+                    "PUBLIC_URL",
+                    "must be an http:// or https:// URL",
+                ))
+            }
+            Err(_) => None,
+        };
+
         Ok(Config {
             listen_addr,
             database_url,
+            window_ms,
+            max_window_ms,
+            max_markers_page,
+            public_url,
         })
     }
 }
 
+/// Read a positive whole number of hours, returned in milliseconds.
+fn hours_from_env(var: &'static str, default_ms: i64) -> Result<i64, ConfigError> {
+    match std::env::var(var) {
+        Ok(value) => match value.parse::<u32>() {
+            Ok(hours) if hours > 0 => Ok(i64::from(hours) * HOUR_MS),
+            _ => Err(ConfigError::Invalid(
+                var,
+                "must be a positive number of hours",
+            )),
+        },
+        Err(_) => Ok(default_ms),
+    }
+}
+
 #[derive(Debug)]
 pub enum ConfigError {
     Invalid(&'static str, &'static str),
@@ -1,9 +1,11 @@
+use futures_util::{Stream, TryStreamExt};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::models::Marker;
+use crate::models::{BBox, Cluster, HeatmapCell, HistogramBucket, Marker, MarkerCursor, SearchHit};
 
 /// Get current time as milliseconds since Unix epoch.
 pub fn current_epoch_ms() -> i64 {
@@ -27,11 +29,18 @@ pub async fn init_pool(database_url: &str) -> Result<SqlitePool, sqlx::Error> {
         .await
 }
 
+/// Migration scripts, applied in order. Each one is idempotent.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations/001_create_marker_log.sql"),
+    include_str!("../migrations/002_create_marker_log_rtree.sql"),
+    include_str!("../migrations/003_create_marker_log_fts.sql"),
+];
+
 /// Run database migrations.
 pub async fn run_migrations(pool: &SqlitePool) -> Result<(), sqlx::Error> {
-    sqlx::query(include_str!("../migrations/001_create_marker_log.sql"))
-        .execute(pool)
-        .await?;
+    for migration in MIGRATIONS {
+        sqlx::query(migration).execute(pool).await?;
+    }
     Ok(())
 }
 
@@ -44,6 +53,20 @@ pub async fn insert_marker(
     lon: f64,
     icon_id: &str,
     label: Option<&str>,
+) -> Result<(Marker, bool), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    insert_marker_in(&mut conn, uuid, lat, lon, icon_id, label).await
+}
+
+/// Like `insert_marker`, on a given connection. Pass an open transaction to
+/// insert several markers atomically.
+pub async fn insert_marker_in(
+    conn: &mut SqliteConnection,
+    uuid: &str,
+    lat: f64,
+    lon: f64,
+    icon_id: &str,
+    label: Option<&str>,
 ) -> Result<(Marker, bool), sqlx::Error> {
     let ts_epoch_ms = current_epoch_ms();
 
@@ -61,7 +84,7 @@ pub async fn insert_marker(
     .bind(lon)
     .bind(icon_id)
     .bind(label)
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
 
     let created = result.rows_affected() > 0;
@@ -71,7 +94,7 @@ pub async fn insert_marker(
         "SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label FROM marker_log WHERE uuid = ?",
     )
     .bind(uuid)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok((marker, created))
@@ -116,92 +139,779 @@ pub async fn insert_marker_with_ts(
     Ok((marker, created))
 }
 
-/// 24 hours in milliseconds.
-const TWENTY_FOUR_HOURS_MS: i64 = 24 * 60 * 60 * 1000;
+/// Append a bounding-box condition on `lat`/`lon` to a WHERE clause.
+///
+/// Candidates come from the `marker_log_rtree` index. Pass the inclusive
+/// `ts_epoch_ms` range the query is limited to, so the index only yields
+/// markers from that range; the exact comparison on `marker_log` then drops
+/// anything the index's 32-bit coordinates let through.
+fn push_bbox_filter(qb: &mut QueryBuilder<'_, Sqlite>, bbox: &BBox, ts_range: Option<(i64, i64)>) {
+    qb.push(" AND id IN (");
+    if bbox.crosses_antimeridian() {
+        // Split into the parts east and west of the antimeridian
+        push_rtree_select(
+            qb,
+            bbox.min_lat,
+            bbox.max_lat,
+            bbox.min_lon,
+            180.0,
+            ts_range,
+        );
+        qb.push(" UNION ALL ");
+        push_rtree_select(
+            qb,
+            bbox.min_lat,
+            bbox.max_lat,
+            -180.0,
+            bbox.max_lon,
+            ts_range,
+        );
+    } else {
+        push_rtree_select(
+            qb,
+            bbox.min_lat,
+            bbox.max_lat,
+            bbox.min_lon,
+            bbox.max_lon,
+            ts_range,
+        );
+    }
+    qb.push(")");
+
+    qb.push(" AND lat BETWEEN ")
+        .push_bind(bbox.min_lat)
+        .push(" AND ")
+        .push_bind(bbox.max_lat);
+
+    if bbox.crosses_antimeridian() {
+        qb.push(" AND (lon >= ")
+            .push_bind(bbox.min_lon)
+            .push(" OR lon <= ")
+            .push_bind(bbox.max_lon)
+            .push(")");
+    } else {
+        qb.push(" AND lon BETWEEN ")
+            .push_bind(bbox.min_lon)
+            .push(" AND ")
+            .push_bind(bbox.max_lon);
+    }
+}
+
+/// Select ids from the index whose boxes overlap the given range.
+fn push_rtree_select(
+    qb: &mut QueryBuilder<'_, Sqlite>,
+    min_lat: f64,
+    max_lat: f64,
+    min_lon: f64,
+    max_lon: f64,
+    ts_range: Option<(i64, i64)>,
+) {
+    qb.push("SELECT id FROM marker_log_rtree WHERE max_lat >= ")
+        .push_bind(min_lat)
+        .push(" AND min_lat <= ")
+        .push_bind(max_lat)
+        .push(" AND max_lon >= ")
+        .push_bind(min_lon)
+        .push(" AND min_lon <= ")
+        .push_bind(max_lon);
+    if let Some((from, to)) = ts_range {
+        qb.push(" AND max_ts >= ")
+            .push_bind(from)
+            .push(" AND min_ts <= ")
+            .push_bind(to);
+    }
+}
+
+/// Get up to `limit` markers from the last `window_ms` milliseconds, optionally within
+/// a bounding box, ordered by `(ts_epoch_ms, id)` and starting after `cursor`.
+/// Returns (markers, max_id, has_more).
+pub async fn get_markers_in_window(
+    pool: &SqlitePool,
+    window_ms: i64,
+    bbox: Option<&BBox>,
+    cursor: Option<MarkerCursor>,
+    limit: i64,
+) -> Result<(Vec<Marker>, i64, bool), sqlx::Error> {
+    let cutoff = current_epoch_ms() - window_ms;
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+        FROM marker_log
+        WHERE ts_epoch_ms >= "#,
+    );
+    qb.push_bind(cutoff);
+    if let Some(cursor) = cursor {
+        qb.push(" AND (ts_epoch_ms, id) > (")
+            .push_bind(cursor.ts_epoch_ms)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, Some((cutoff, i64::MAX)));
+    }
+    qb.push(" ORDER BY ts_epoch_ms ASC, id ASC LIMIT ")
+        .push_bind(limit + 1); // Fetch one extra to check if there's more
+
+    let markers = qb.build_query_as::<Marker>().fetch_all(pool).await?;
+
+    let has_more = markers.len() > limit as usize;
+    let markers: Vec<Marker> = markers.into_iter().take(limit as usize).collect();
+
+    // Get max_id
+    let max_id: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM marker_log")
+        .fetch_one(pool)
+        .await?;
 
-/// Get markers from the last 24 hours.
-pub async fn get_markers_last_24h(pool: &SqlitePool) -> Result<(Vec<Marker>, i64), sqlx::Error> {
-    let cutoff = current_epoch_ms() - TWENTY_FOUR_HOURS_MS;
+    Ok((markers, max_id, has_more))
+}
+
+/// Get the newest `limit` markers from the last `window_ms` milliseconds within a
+/// bounding box, returned oldest first. Returns (markers, has_more).
+pub async fn get_latest_markers_in_window(
+    pool: &SqlitePool,
+    window_ms: i64,
+    bbox: &BBox,
+    limit: i64,
+) -> Result<(Vec<Marker>, bool), sqlx::Error> {
+    let cutoff = current_epoch_ms() - window_ms;
 
-    let markers = sqlx::query_as::<_, Marker>(
+    let mut qb = QueryBuilder::new(
         r#"
         SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
         FROM marker_log
-        WHERE ts_epoch_ms >= ?
-        ORDER BY ts_epoch_ms ASC
+        WHERE ts_epoch_ms >= "#,
+    );
+    qb.push_bind(cutoff);
+    push_bbox_filter(&mut qb, bbox, Some((cutoff, i64::MAX)));
+    qb.push(" ORDER BY ts_epoch_ms DESC, id DESC LIMIT ")
+        .push_bind(limit + 1); // Fetch one extra to check if there's more
+
+    let mut markers = qb.build_query_as::<Marker>().fetch_all(pool).await?;
+
+    let has_more = markers.len() > limit as usize;
+    markers.truncate(limit as usize);
+    markers.reverse();
+
+    Ok((markers, has_more))
+}
+
+/// Version of the visibility window: (log head id, id of the oldest marker in the
+/// window). Both only move forward, and the window's contents change exactly
+/// when one of them does.
+pub async fn get_window_version(
+    pool: &SqlitePool,
+    window_ms: i64,
+) -> Result<(i64, i64), sqlx::Error> {
+    let cutoff = current_epoch_ms() - window_ms;
+
+    sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COALESCE(MAX(id), 0) FROM marker_log),
+            COALESCE((
+                SELECT id FROM marker_log
+                WHERE ts_epoch_ms >= ?
+                ORDER BY ts_epoch_ms ASC, id ASC
+                LIMIT 1
+            ), 0)
         "#,
     )
     .bind(cutoff)
-    .fetch_all(pool)
-    .await?;
+    .fetch_one(pool)
+    .await
+}
 
-    // Get max_id
-    let max_id: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM marker_log")
+/// Highest log id, or 0 for an empty log.
+pub async fn get_log_head(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM marker_log")
         .fetch_one(pool)
+        .await
+}
+
+/// Group markers from the last `window_ms` milliseconds into `cell_deg` grid cells.
+///
+/// Cells are aligned to (-180, -90). Counts per cell and icon_id are computed in
+/// SQL; the centroid is the mean position of the cell's markers. Returns the
+/// `limit` largest clusters, largest first, and whether there were more.
+pub async fn get_marker_clusters(
+    pool: &SqlitePool,
+    window_ms: i64,
+    cell_deg: f64,
+    bbox: Option<&BBox>,
+    limit: i64,
+) -> Result<(Vec<Cluster>, bool), sqlx::Error> {
+    let cutoff = current_epoch_ms() - window_ms;
+
+    // Offsets are non-negative, so the integer cast floors
+    let mut qb = QueryBuilder::new("WITH m AS (SELECT CAST((lon + 180.0) / ");
+    qb.push_bind(cell_deg)
+        .push(" AS INTEGER) AS cell_x, CAST((lat + 90.0) / ")
+        .push_bind(cell_deg)
+        .push(
+            r#" AS INTEGER) AS cell_y, icon_id, lat, lon
+        FROM marker_log
+        WHERE ts_epoch_ms >= "#,
+        )
+        .push_bind(cutoff);
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, Some((cutoff, i64::MAX)));
+    }
+    // Only the largest cells are broken down per icon
+    qb.push(
+        r#"),
+        top AS (
+            SELECT cell_x, cell_y FROM m
+            GROUP BY cell_x, cell_y
+            ORDER BY COUNT(*) DESC, cell_x, cell_y
+            LIMIT "#,
+    )
+    .push_bind(limit + 1) // Fetch one extra to check if there's more
+    .push(
+        r#"
+        )
+        SELECT cell_x, cell_y, icon_id, COUNT(*), SUM(lat), SUM(lon)
+        FROM m JOIN top USING (cell_x, cell_y)
+        GROUP BY cell_x, cell_y, icon_id"#,
+    );
+
+    let rows = qb
+        .build_query_as::<(i64, i64, String, i64, f64, f64)>()
+        .fetch_all(pool)
         .await?;
 
-    Ok((markers, max_id))
+    // Merge the per-icon rows of each cell
+    let mut cells: BTreeMap<(i64, i64), (Cluster, f64, f64)> = BTreeMap::new();
+    for (cell_x, cell_y, icon_id, count, sum_lat, sum_lon) in rows {
+        let (cluster, lat_total, lon_total) = cells.entry((cell_x, cell_y)).or_insert_with(|| {
+            (
+                Cluster {
+                    lat: 0.0,
+                    lon: 0.0,
+                    count: 0,
+                    icons: BTreeMap::new(),
+                },
+                0.0,
+                0.0,
+            )
+        });
+        cluster.count += count;
+        cluster.icons.insert(icon_id, count);
+        *lat_total += sum_lat;
+        *lon_total += sum_lon;
+    }
+
+    let mut clusters: Vec<Cluster> = cells
+        .into_values()
+        .map(|(mut cluster, lat_total, lon_total)| {
+            cluster.lat = lat_total / cluster.count as f64;
+            cluster.lon = lon_total / cluster.count as f64;
+            cluster
+        })
+        .collect();
+    // Stable, so ties stay in cell order like in `top`
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    let has_more = clusters.len() > limit as usize;
+    clusters.truncate(limit as usize);
+
+    Ok((clusters, has_more))
+}
+
+/// Age steps per half-life when grouping markers for decay weighting.
+const DECAY_STEPS_PER_HALF_LIFE: i64 = 8;
+
+/// Density grid of markers with `start <= ts_epoch_ms <= end` in `cell_deg` cells.
+///
+/// Without `half_life_ms` each marker weighs 1. With it, a marker's weight
+/// halves for every `half_life_ms` it is older than `end`. SQLite has no
+/// `exp()`, so markers are grouped by cell and age step in SQL and each group
+/// is weighted at its step midpoint here. Only non-empty cells are returned.
+pub async fn get_heatmap(
+    pool: &SqlitePool,
+    start: i64,
+    end: i64,
+    cell_deg: f64,
+    half_life_ms: Option<i64>,
+    bbox: Option<&BBox>,
+) -> Result<Vec<HeatmapCell>, sqlx::Error> {
+    let step_ms = half_life_ms.map(|h| (h / DECAY_STEPS_PER_HALF_LIFE).max(1));
+
+    // Offsets are non-negative, so the integer casts floor
+    let mut qb = QueryBuilder::new("SELECT CAST((lon + 180.0) / ");
+    qb.push_bind(cell_deg)
+        .push(" AS INTEGER) AS cell_x, CAST((lat + 90.0) / ")
+        .push_bind(cell_deg)
+        .push(" AS INTEGER) AS cell_y, ");
+    match step_ms {
+        Some(step_ms) => {
+            qb.push("(")
+                .push_bind(end)
+                .push(" - ts_epoch_ms) / ")
+                .push_bind(step_ms);
+        }
+        None => {
+            qb.push("0");
+        }
+    }
+    qb.push(" AS age_step, COUNT(*) FROM marker_log WHERE ts_epoch_ms >= ")
+        .push_bind(start)
+        .push(" AND ts_epoch_ms <= ")
+        .push_bind(end);
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, Some((start, end)));
+    }
+    qb.push(" GROUP BY cell_x, cell_y, age_step");
+
+    let rows = qb
+        .build_query_as::<(i64, i64, i64, i64)>()
+        .fetch_all(pool)
+        .await?;
+
+    let mut cells: BTreeMap<(i64, i64), HeatmapCell> = BTreeMap::new();
+    for (cell_x, cell_y, age_step, count) in rows {
+        let weight = match (half_life_ms, step_ms) {
+            (Some(half_life_ms), Some(step_ms)) => {
+                let age_ms = (age_step as f64 + 0.5) * step_ms as f64;
+                count as f64 * 0.5f64.powf(age_ms / half_life_ms as f64)
+            }
+            _ => count as f64,
+        };
+        let cell = cells
+            .entry((cell_x, cell_y))
+            .or_insert_with(|| HeatmapCell {
+                lat: (-90.0 + (cell_y as f64 + 0.5) * cell_deg).min(90.0),
+                lon: (-180.0 + (cell_x as f64 + 0.5) * cell_deg).min(180.0),
+                count: 0,
+                weight: 0.0,
+            });
+        cell.count += count;
+        cell.weight += weight;
+    }
+
+    Ok(cells.into_values().collect())
 }
 
-/// Get markers visible at a specific point in time (24h window ending at that time).
+/// Count markers per `bucket_ms` bucket over `from..=to`, optionally for one icon_id.
+/// Buckets start at `from`; empty buckets are included with a zero count.
+/// `bucket_count` is the validated number of buckets covering the range.
+pub async fn get_histogram(
+    pool: &SqlitePool,
+    from: i64,
+    to: i64,
+    bucket_ms: i64,
+    bucket_count: i64,
+    icon_id: Option<&str>,
+) -> Result<Vec<HistogramBucket>, sqlx::Error> {
+    // Offsets are non-negative, so integer division floors
+    let mut qb = QueryBuilder::new("SELECT (ts_epoch_ms - ");
+    qb.push_bind(from)
+        .push(") / ")
+        .push_bind(bucket_ms)
+        .push(" AS bucket, COUNT(*) FROM marker_log WHERE ts_epoch_ms >= ")
+        .push_bind(from)
+        .push(" AND ts_epoch_ms <= ")
+        .push_bind(to);
+    if let Some(icon_id) = icon_id {
+        qb.push(" AND icon_id = ").push_bind(icon_id);
+    }
+    qb.push(" GROUP BY bucket");
+
+    let counts = qb.build_query_as::<(i64, i64)>().fetch_all(pool).await?;
+
+    // Bucket starts are within from..=to, so they don't overflow
+    let mut buckets: Vec<HistogramBucket> = (0..bucket_count)
+        .map(|i| HistogramBucket {
+            start_ms: from + i * bucket_ms,
+            count: 0,
+        })
+        .collect();
+    for (bucket, count) in counts {
+        if let Some(b) = usize::try_from(bucket)
+            .ok()
+            .and_then(|i| buckets.get_mut(i))
+        {
+            b.count = count;
+        }
+    }
+
+    Ok(buckets)
+}
+
+/// Get markers visible at a specific point in time (`window_ms` window ending at that time).
 /// `at_epoch_ms` is the end of the window in milliseconds since Unix epoch.
 pub async fn get_markers_at(
     pool: &SqlitePool,
     at_epoch_ms: i64,
+    window_ms: i64,
+    bbox: Option<&BBox>,
 ) -> Result<Vec<Marker>, sqlx::Error> {
-    let start = at_epoch_ms - TWENTY_FOUR_HOURS_MS;
+    let start = at_epoch_ms - window_ms;
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+        FROM marker_log
+        WHERE ts_epoch_ms <= "#,
+    );
+    qb.push_bind(at_epoch_ms)
+        .push(" AND ts_epoch_ms >= ")
+        .push_bind(start);
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, Some((start, at_epoch_ms)));
+    }
+    qb.push(" ORDER BY ts_epoch_ms ASC");
+
+    let markers = qb.build_query_as::<Marker>().fetch_all(pool).await?;
+
+    Ok(markers)
+}
 
-    let markers = sqlx::query_as::<_, Marker>(
+/// Get a marker by uuid, regardless of its age.
+pub async fn get_marker_by_uuid(
+    pool: &SqlitePool,
+    uuid: &str,
+) -> Result<Option<Marker>, sqlx::Error> {
+    sqlx::query_as::<_, Marker>(
         r#"
         SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
         FROM marker_log
-        WHERE ts_epoch_ms <= ?
-          AND ts_epoch_ms >= ?
-        ORDER BY ts_epoch_ms ASC
+        WHERE uuid = ?
         "#,
     )
-    .bind(at_epoch_ms)
-    .bind(start)
-    .fetch_all(pool)
-    .await?;
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await
+}
 
-    Ok(markers)
+/// Get the markers with the given uuids, regardless of their age.
+/// Unknown uuids are left out; the order of the result is unspecified.
+pub async fn get_markers_by_uuids(
+    pool: &SqlitePool,
+    uuids: &[String],
+) -> Result<Vec<Marker>, sqlx::Error> {
+    if uuids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+        FROM marker_log
+        WHERE uuid IN ("#,
+    );
+    let mut separated = qb.separated(", ");
+    for uuid in uuids {
+        separated.push_bind(uuid);
+    }
+    qb.push(")");
+
+    qb.build_query_as::<Marker>().fetch_all(pool).await
+}
+
+/// Get markers with `from <= ts_epoch_ms <= to`, ordered by `(ts_epoch_ms, id)`.
+/// Pages start after `cursor` when given. Returns (markers, has_more).
+pub async fn get_markers_range(
+    pool: &SqlitePool,
+    from: i64,
+    to: i64,
+    cursor: Option<MarkerCursor>,
+    limit: i64,
+    bbox: Option<&BBox>,
+) -> Result<(Vec<Marker>, bool), sqlx::Error> {
+    // Clamp limit to MAX_LIMIT
+    let limit = limit.min(MAX_LIMIT);
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+        FROM marker_log
+        WHERE ts_epoch_ms >= "#,
+    );
+    qb.push_bind(from)
+        .push(" AND ts_epoch_ms <= ")
+        .push_bind(to);
+    if let Some(cursor) = cursor {
+        qb.push(" AND (ts_epoch_ms, id) > (")
+            .push_bind(cursor.ts_epoch_ms)
+            .push(", ")
+            .push_bind(cursor.id)
+            .push(")");
+    }
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, Some((from, to)));
+    }
+    qb.push(" ORDER BY ts_epoch_ms ASC, id ASC LIMIT ")
+        .push_bind(limit + 1); // Fetch one extra to check if there's more
+
+    let markers = qb.build_query_as::<Marker>().fetch_all(pool).await?;
+
+    let has_more = markers.len() > limit as usize;
+    let markers: Vec<Marker> = markers.into_iter().take(limit as usize).collect();
+
+    Ok((markers, has_more))
+}
+
+/// Stream markers with `from <= ts_epoch_ms <= to`, ordered by `(ts_epoch_ms, id)`,
+/// optionally of one icon and within a bounding box. Rows are read through a
+/// database cursor, so there is no limit.
+pub fn stream_markers_range(
+    pool: SqlitePool,
+    from: i64,
+    to: i64,
+    icon_id: Option<String>,
+    bbox: Option<BBox>,
+) -> impl Stream<Item = Result<Marker, sqlx::Error>> + Send + 'static {
+    async_stream::try_stream! {
+        let mut qb = QueryBuilder::new(
+            r#"
+            SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+            FROM marker_log
+            WHERE ts_epoch_ms >= "#,
+        );
+        qb.push_bind(from)
+            .push(" AND ts_epoch_ms <= ")
+            .push_bind(to);
+        if let Some(icon_id) = &icon_id {
+            qb.push(" AND icon_id = ").push_bind(icon_id.as_str());
+        }
+        if let Some(bbox) = &bbox {
+            push_bbox_filter(&mut qb, bbox, Some((from, to)));
+        }
+        qb.push(" ORDER BY ts_epoch_ms ASC, id ASC");
+
+        let mut rows = qb.build_query_as::<Marker>().fetch(&pool);
+        while let Some(marker) = rows.try_next().await? {
+            yield marker;
+        }
+    }
+}
+
+/// Stream every log entry after `after_id`, in id order. Rows are read through a
+/// database cursor from a single snapshot, so there is no limit and entries
+/// committed meanwhile are left for the next dump.
+pub fn stream_log_after(
+    pool: SqlitePool,
+    after_id: i64,
+) -> impl Stream<Item = Result<Marker, sqlx::Error>> + Send + 'static {
+    async_stream::try_stream! {
+        let mut rows = sqlx::query_as::<_, Marker>(
+            r#"
+            SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+            FROM marker_log
+            WHERE id > ?
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(after_id)
+        .fetch(&pool);
+        while let Some(marker) = rows.try_next().await? {
+            yield marker;
+        }
+    }
 }
 
 /// Maximum allowed limit for pagination.
 pub const MAX_LIMIT: i64 = 1000;
 
-/// Get log entries after a given id (for polling/sync).
+/// Get log entries after a given id (for polling/sync), optionally within a bounding box.
+///
+/// With a bounding box, `max_id` advances past entries outside the box once the
+/// caller has caught up, so pollers don't rescan the same rows.
 pub async fn get_log_after(
     pool: &SqlitePool,
     after_id: i64,
     limit: i64,
+    bbox: Option<&BBox>,
 ) -> Result<(Vec<Marker>, i64, bool), sqlx::Error> {
     // Clamp limit to MAX_LIMIT
     let limit = limit.min(MAX_LIMIT);
 
-    let entries = sqlx::query_as::<_, Marker>(
+    // Read entries and the log head from the same snapshot
+    let mut tx = pool.begin().await?;
+
+    let mut qb = QueryBuilder::new(
         r#"
         SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
         FROM marker_log
-        WHERE id > ?
-        ORDER BY id ASC
-        LIMIT ?
-        "#,
-    )
-    .bind(after_id)
-    .bind(limit + 1) // Fetch one extra to check if there's more
-    .fetch_all(pool)
-    .await?;
+        WHERE id > "#,
+    );
+    qb.push_bind(after_id);
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, None);
+    }
+    qb.push(" ORDER BY id ASC LIMIT ").push_bind(limit + 1); // Fetch one extra to check if there's more
+
+    let entries = qb.build_query_as::<Marker>().fetch_all(&mut *tx).await?;
+
+    let has_more = entries.len() > limit as usize;
+    let entries: Vec<Marker> = entries.into_iter().take(limit as usize).collect();
+
+    let max_id = if bbox.is_some() && !has_more {
+        // Every matching entry up to the head has been returned
+        let head: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM marker_log")
+            .fetch_one(&mut *tx)
+            .await?;
+        head.max(after_id)
+    } else {
+        entries.last().map(|m| m.id).unwrap_or(after_id)
+    };
+
+    tx.commit().await?;
+
+    Ok((entries, max_id, has_more))
+}
+
+/// Get up to `limit` log entries with id below `before_id`, newest first;
+/// `before_id == 0` starts from the tail of the log.
+/// Returns (entries, max_id, has_more), where max_id is where a forward reader
+/// can resume: the log head when starting from the tail, otherwise the
+/// newest entry returned (or `before_id - 1` if none).
+pub async fn get_log_before(
+    pool: &SqlitePool,
+    before_id: i64,
+    limit: i64,
+    bbox: Option<&BBox>,
+) -> Result<(Vec<Marker>, i64, bool), sqlx::Error> {
+    // Clamp limit to MAX_LIMIT
+    let limit = limit.min(MAX_LIMIT);
+    let upper = if before_id == 0 { i64::MAX } else { before_id };
+
+    // Read entries and the log head from the same snapshot
+    let mut tx = pool.begin().await?;
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT id, uuid, ts_epoch_ms, lat, lon, icon_id, label
+        FROM marker_log
+        WHERE id < "#,
+    );
+    qb.push_bind(upper);
+    if let Some(bbox) = bbox {
+        push_bbox_filter(&mut qb, bbox, None);
+    }
+    qb.push(" ORDER BY id DESC LIMIT ").push_bind(limit + 1); // Fetch one extra to check if there's more
+
+    let entries = qb.build_query_as::<Marker>().fetch_all(&mut *tx).await?;
 
     let has_more = entries.len() > limit as usize;
     let entries: Vec<Marker> = entries.into_iter().take(limit as usize).collect();
 
-    let max_id = entries.last().map(|m| m.id).unwrap_or(after_id);
+    let max_id = if before_id == 0 {
+        sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM marker_log")
+            .fetch_one(&mut *tx)
+            .await?
+    } else {
+        entries
+            .first()
+            .map(|m| m.id)
+            .unwrap_or((before_id - 1).max(0))
+    };
+
+    tx.commit().await?;
 
     Ok((entries, max_id, has_more))
 }
 
+/// Turn free text into an FTS5 query: every word must match, as a prefix
+/// (so "MMSI 257" finds "MMSI 257123456"). Operators in the input are treated
+/// as plain text.
+fn fts_query(q: &str) -> String {
+    q.split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Markers wrapping matches in FTS5 highlight output, replaced after escaping.
+const HIGHLIGHT_START: char = '\u{2}';
+const HIGHLIGHT_END: char = '\u{3}';
+
+/// Prefix of a control character escaped in the label index (see migration
+/// 003), so labels containing the highlight markers themselves are kept apart.
+const HIGHLIGHT_ESCAPE: char = '\u{1}';
+
+/// HTML-escape a highlighted label and turn the match markers into `<mark>` tags.
+fn render_highlight(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len() + 16);
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            HIGHLIGHT_ESCAPE => match chars.next() {
+                Some('\u{4}') => out.push(HIGHLIGHT_START),
+                Some('\u{5}') => out.push(HIGHLIGHT_END),
+                _ => out.push(HIGHLIGHT_ESCAPE),
+            },
+            HIGHLIGHT_START => out.push_str("<mark>"),
+            HIGHLIGHT_END => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Search marker labels across the whole log, best matches first.
+/// `from`/`to` optionally bound `ts_epoch_ms` (inclusive).
+/// Returns (hits, has_more).
+pub async fn search_labels(
+    pool: &SqlitePool,
+    q: &str,
+    from: Option<i64>,
+    to: Option<i64>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<SearchHit>, bool), sqlx::Error> {
+    let limit = limit.min(MAX_LIMIT);
+
+    let mut qb = QueryBuilder::new(
+        r#"
+        SELECT m.id, m.uuid, m.ts_epoch_ms, m.lat, m.lon, m.icon_id, m.label,
+               highlight(marker_log_fts, 0, "#,
+    );
+    qb.push_bind(HIGHLIGHT_START.to_string())
+        .push(", ")
+        .push_bind(HIGHLIGHT_END.to_string())
+        .push(
+            r#") AS highlight,
+               -bm25(marker_log_fts) AS score
+        FROM marker_log_fts
+        JOIN marker_log m ON m.id = marker_log_fts.rowid
+        WHERE marker_log_fts MATCH "#,
+        )
+        .push_bind(fts_query(q));
+    if let Some(from) = from {
+        qb.push(" AND m.ts_epoch_ms >= ").push_bind(from);
+    }
+    if let Some(to) = to {
+        qb.push(" AND m.ts_epoch_ms <= ").push_bind(to);
+    }
+    qb.push(" ORDER BY bm25(marker_log_fts), m.id DESC LIMIT ")
+        .push_bind(limit + 1) // Fetch one extra to check if there's more
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    let hits = qb.build_query_as::<SearchHit>().fetch_all(pool).await?;
+
+    let has_more = hits.len() > limit as usize;
+    let hits: Vec<SearchHit> = hits
+        .into_iter()
+        .take(limit as usize)
+        .map(|hit| SearchHit {
+            highlight: render_highlight(&hit.highlight),
+            ..hit
+        })
+        .collect();
+
+    Ok((hits, has_more))
+}
+
 /// Get current server time as epoch milliseconds.
 pub fn get_server_time_ms() -> i64 {
     current_epoch_ms()
@@ -211,6 +921,8 @@ pub fn get_server_time_ms() -> i64 {
 mod tests {
     use super::*;
 
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
     /// Create a test database with in-memory SQLite.
     async fn setup_test_db() -> SqlitePool {
         let pool = init_pool("sqlite::memory:").await.unwrap();
@@ -299,17 +1011,19 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_markers_last_24h_empty() {
+    async fn test_get_markers_in_window_empty() {
         let pool = setup_test_db().await;
 
-        let (markers, max_id) = get_markers_last_24h(&pool).await.unwrap();
+        let (markers, max_id, _) = get_markers_in_window(&pool, DAY_MS, None, None, 1000)
+            .await
+            .unwrap();
 
         assert!(markers.is_empty());
         assert_eq!(max_id, 0);
     }
 
     #[tokio::test]
-    async fn test_get_markers_last_24h() {
+    async fn test_get_markers_in_window() {
         let pool = setup_test_db().await;
 
         // Insert two markers
@@ -320,7 +1034,9 @@ mod tests {
             .await
             .unwrap();
 
-        let (markers, max_id) = get_markers_last_24h(&pool).await.unwrap();
+        let (markers, max_id, _) = get_markers_in_window(&pool, DAY_MS, None, None, 1000)
+            .await
+            .unwrap();
 
         assert_eq!(markers.len(), 2);
         assert_eq!(max_id, 2);
@@ -329,7 +1045,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get_markers_last_24h_excludes_old() {
+    async fn test_get_markers_in_window_excludes_old() {
         let pool = setup_test_db().await;
 
         let now = current_epoch_ms();
@@ -345,7 +1061,9 @@ mod tests {
             .await
             .unwrap();
 
-        let (markers, _) = get_markers_last_24h(&pool).await.unwrap();
+        let (markers, _, _) = get_markers_in_window(&pool, DAY_MS, None, None, 1000)
+            .await
+            .unwrap();
 
         // Only the new marker should be included
         assert_eq!(markers.len(), 1);
@@ -356,7 +1074,7 @@ mod tests {
     async fn test_get_log_after_empty() {
         let pool = setup_test_db().await;
 
-        let (entries, max_id, has_more) = get_log_after(&pool, 0, 100).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 0, 100, None).await.unwrap();
 
         assert!(entries.is_empty());
         assert_eq!(max_id, 0);
@@ -379,13 +1097,13 @@ mod tests {
             .unwrap();
 
         // Get all entries after id 0
-        let (entries, max_id, has_more) = get_log_after(&pool, 0, 100).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 0, 100, None).await.unwrap();
         assert_eq!(entries.len(), 3);
         assert_eq!(max_id, 3);
         assert!(!has_more);
 
         // Get entries after id 1
-        let (entries, max_id, has_more) = get_log_after(&pool, 1, 100).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 1, 100, None).await.unwrap();
         assert_eq!(entries.len(), 2);
         assert_eq!(entries[0].uuid, "uuid-2");
         assert_eq!(entries[1].uuid, "uuid-3");
@@ -393,7 +1111,7 @@ mod tests {
         assert!(!has_more);
 
         // Get entries after id 3 (none)
-        let (entries, max_id, has_more) = get_log_after(&pool, 3, 100).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 3, 100, None).await.unwrap();
         assert!(entries.is_empty());
         assert_eq!(max_id, 3);
         assert!(!has_more);
@@ -418,19 +1136,19 @@ mod tests {
         }
 
         // Get with limit 2
-        let (entries, max_id, has_more) = get_log_after(&pool, 0, 2).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 0, 2, None).await.unwrap();
         assert_eq!(entries.len(), 2);
         assert_eq!(max_id, 2);
         assert!(has_more);
 
         // Get next page
-        let (entries, max_id, has_more) = get_log_after(&pool, 2, 2).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 2, 2, None).await.unwrap();
         assert_eq!(entries.len(), 2);
         assert_eq!(max_id, 4);
         assert!(has_more);
 
         // Get last page
-        let (entries, max_id, has_more) = get_log_after(&pool, 4, 2).await.unwrap();
+        let (entries, max_id, has_more) = get_log_after(&pool, 4, 2, None).await.unwrap();
         assert_eq!(entries.len(), 1);
         assert_eq!(max_id, 5);
         assert!(!has_more);
@@ -455,7 +1173,7 @@ mod tests {
         }
 
         // Request with very high limit - should be clamped
-        let (entries, _, _) = get_log_after(&pool, 0, 100000).await.unwrap();
+        let (entries, _, _) = get_log_after(&pool, 0, 100000, None).await.unwrap();
         assert_eq!(entries.len(), 5); // All 5 markers, not capped because we only have 5
     }
 
@@ -495,23 +1213,685 @@ mod tests {
             .unwrap();
 
         // Get markers at current time (last 24h) - should exclude uuid-old (30h ago)
-        let markers = get_markers_at(&pool, now).await.unwrap();
+        let markers = get_markers_at(&pool, now, DAY_MS, None).await.unwrap();
         assert_eq!(markers.len(), 2); // uuid-mid and uuid-new
 
         // Get markers at 12 hours ago - window is (36h ago, 12h ago]
         // uuid-old (30h ago) is within this window
         // uuid-mid (12h ago) is within this window
         // uuid-new (now) is NOT within this window (it's in the future)
-        let markers = get_markers_at(&pool, twelve_hours_ago).await.unwrap();
+        let markers = get_markers_at(&pool, twelve_hours_ago, DAY_MS, None)
+            .await
+            .unwrap();
         assert_eq!(markers.len(), 2); // uuid-old and uuid-mid
 
         // Get markers at 25 hours ago - window is (49h ago, 25h ago]
         // uuid-old (30h ago) IS within this window (49 > 30 > 25)
         // uuid-mid (12h ago) is NOT within this window (it's in the future relative to 25h ago)
         let twenty_five_hours_ago = now - (25 * 60 * 60 * 1000);
-        let markers = get_markers_at(&pool, twenty_five_hours_ago).await.unwrap();
+        let markers = get_markers_at(&pool, twenty_five_hours_ago, DAY_MS, None)
+            .await
+            .unwrap();
         assert_eq!(markers.len(), 1); // just uuid-old
         assert_eq!(markers[0].uuid, "uuid-old");
+
+        // A 2-hour window at the current time only reaches uuid-new
+        let markers = get_markers_at(&pool, now, 2 * 60 * 60 * 1000, None)
+            .await
+            .unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].uuid, "uuid-new");
+    }
+
+    #[tokio::test]
+    async fn test_get_marker_clusters() {
+        let pool = setup_test_db().await;
+        insert_bbox_fixtures(&pool).await;
+
+        // 45° cells: Oslo and Bergen share one, Fiji is split by the antimeridian
+        let (clusters, has_more) = get_marker_clusters(&pool, DAY_MS, 45.0, None, 100)
+            .await
+            .unwrap();
+        assert_eq!(clusters.len(), 4);
+        assert!(!has_more);
+        assert_eq!(clusters[0].count, 2);
+        assert!((clusters[0].lat - 60.15).abs() < 1e-9);
+        assert!((clusters[0].lon - 8.035).abs() < 1e-9);
+        assert_eq!(clusters[0].icons.get("marker"), Some(&1));
+        assert_eq!(clusters[0].icons.get("ship"), Some(&1));
+
+        // Finer cells separate Oslo and Bergen
+        let norway = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+        let (clusters, _) = get_marker_clusters(&pool, DAY_MS, 1.0, Some(&norway), 100)
+            .await
+            .unwrap();
+        assert_eq!(clusters.len(), 2);
+        assert!(clusters.iter().all(|c| c.count == 1));
+
+        // Capped to the largest clusters
+        let (clusters, has_more) = get_marker_clusters(&pool, DAY_MS, 45.0, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].count, 2);
+        assert_eq!(clusters[0].icons.len(), 2);
+        assert!(has_more);
+    }
+
+    #[tokio::test]
+    async fn test_get_histogram() {
+        let pool = setup_test_db().await;
+
+        let base = 1705665600000;
+        for (uuid, offset, icon_id) in [
+            ("uuid-1", 0, "marker"),
+            ("uuid-2", 500, "ship"),
+            ("uuid-3", 2500, "marker"),
+            ("uuid-4", 3000, "marker"), // on `to`, still counted
+            ("uuid-5", 3001, "marker"), // after `to`
+        ] {
+            insert_marker_with_ts(&pool, uuid, base + offset, 59.91, 10.75, icon_id, None)
+                .await
+                .unwrap();
+        }
+
+        let buckets = get_histogram(&pool, base, base + 3000, 1000, 4, None)
+            .await
+            .unwrap();
+        let counts: Vec<i64> = buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![2, 0, 1, 1]);
+        assert_eq!(buckets[2].start_ms, base + 2000);
+
+        let buckets = get_histogram(&pool, base, base + 3000, 1000, 4, Some("ship"))
+            .await
+            .unwrap();
+        let counts: Vec<i64> = buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![1, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_get_heatmap() {
+        let pool = setup_test_db().await;
+
+        let hour = 60 * 60 * 1000;
+        let now = 1705665600000;
+        for (uuid, ts, lat, lon) in [
+            ("uuid-oslo-new", now, 59.91, 10.75),
+            ("uuid-oslo-old", now - 2 * hour, 59.95, 10.70),
+            ("uuid-nyc", now - hour, 40.71, -74.01),
+        ] {
+            insert_marker_with_ts(&pool, uuid, ts, lat, lon, "marker", None)
+                .await
+                .unwrap();
+        }
+
+        let cells = get_heatmap(&pool, now - DAY_MS, now, 1.0, None, None)
+            .await
+            .unwrap();
+        assert_eq!(cells.len(), 2);
+        let oslo = cells.iter().find(|c| c.count == 2).unwrap();
+        assert_eq!((oslo.lat, oslo.lon), (59.5, 10.5));
+        assert_eq!(oslo.weight, 2.0);
+
+        // One-hour half-life: weights near 1 and 1/4, within a step's rounding
+        let cells = get_heatmap(&pool, now - DAY_MS, now, 1.0, Some(hour), None)
+            .await
+            .unwrap();
+        let oslo = cells.iter().find(|c| c.count == 2).unwrap();
+        assert!((oslo.weight - 1.25).abs() < 0.1, "{}", oslo.weight);
+        let nyc = cells.iter().find(|c| c.count == 1).unwrap();
+        assert!((nyc.weight - 0.5).abs() < 0.05, "{}", nyc.weight);
+    }
+
+    #[tokio::test]
+    async fn test_get_markers_in_window_pagination() {
+        let pool = setup_test_db().await;
+
+        // Three markers sharing a timestamp, so paging must tie-break on id
+        let now = current_epoch_ms();
+        for uuid in ["uuid-1", "uuid-2", "uuid-3"] {
+            insert_marker_with_ts(&pool, uuid, now, 59.91, 10.75, "marker", None)
+                .await
+                .unwrap();
+        }
+
+        let (page, max_id, has_more) = get_markers_in_window(&pool, DAY_MS, None, None, 2)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(max_id, 3);
+        assert!(has_more);
+
+        let cursor = MarkerCursor::after(&page[1]);
+        let (page, _, has_more) = get_markers_in_window(&pool, DAY_MS, None, Some(cursor), 2)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].uuid, "uuid-3");
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_get_markers_by_uuid() {
+        let pool = setup_test_db().await;
+
+        // Older than any window
+        insert_marker_with_ts(&pool, "uuid-old", 1000, 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+        insert_marker(&pool, "uuid-new", 60.39, 5.32, "ship", None)
+            .await
+            .unwrap();
+
+        let marker = get_marker_by_uuid(&pool, "uuid-old").await.unwrap();
+        assert_eq!(marker.map(|m| m.ts_epoch_ms), Some(1000));
+        assert!(get_marker_by_uuid(&pool, "uuid-missing")
+            .await
+            .unwrap()
+            .is_none());
+
+        let uuids = ["uuid-new", "uuid-missing", "uuid-old"].map(String::from);
+        let mut markers = get_markers_by_uuids(&pool, &uuids).await.unwrap();
+        markers.sort_by_key(|m| m.id);
+        let found: Vec<&str> = markers.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(found, vec!["uuid-old", "uuid-new"]);
+
+        assert!(get_markers_by_uuids(&pool, &[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_window_version() {
+        let pool = setup_test_db().await;
+        assert_eq!(get_window_version(&pool, DAY_MS).await.unwrap(), (0, 0));
+
+        let now = current_epoch_ms();
+        insert_marker_with_ts(
+            &pool,
+            "uuid-old",
+            now - 2 * DAY_MS,
+            59.91,
+            10.75,
+            "marker",
+            None,
+        )
+        .await
+        .unwrap();
+        insert_marker_with_ts(&pool, "uuid-1", now - 1000, 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+        insert_marker_with_ts(&pool, "uuid-2", now, 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+
+        // The oldest marker is outside the window
+        assert_eq!(get_window_version(&pool, DAY_MS).await.unwrap(), (3, 2));
+        // A shorter window has already dropped uuid-1
+        assert_eq!(get_window_version(&pool, 500).await.unwrap(), (3, 3));
+        assert_eq!(get_log_head(&pool).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_log_before() {
+        let pool = setup_test_db().await;
+
+        for i in 1..=5 {
+            insert_marker(&pool, &format!("uuid-{}", i), 59.91, 10.75, "marker", None)
+                .await
+                .unwrap();
+        }
+
+        // From the tail
+        let (entries, max_id, has_more) = get_log_before(&pool, 0, 2, None).await.unwrap();
+        let ids: Vec<i64> = entries.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![5, 4]);
+        assert_eq!(max_id, 5);
+        assert!(has_more);
+
+        // Older pages
+        let (entries, _, has_more) = get_log_before(&pool, 4, 2, None).await.unwrap();
+        let ids: Vec<i64> = entries.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![3, 2]);
+        assert!(has_more);
+
+        let (entries, _, has_more) = get_log_before(&pool, 2, 2, None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, 1);
+        assert!(!has_more);
+
+        let (entries, max_id, has_more) = get_log_before(&pool, 1, 2, None).await.unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(max_id, 0);
+        assert!(!has_more);
+    }
+
+    /// Insert markers in Oslo, Bergen, New York and Fiji (both sides of 180).
+    async fn insert_bbox_fixtures(pool: &SqlitePool) {
+        insert_marker(pool, "uuid-oslo", 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+        insert_marker(pool, "uuid-bergen", 60.39, 5.32, "ship", None)
+            .await
+            .unwrap();
+        insert_marker(pool, "uuid-nyc", 40.71, -74.01, "marker", None)
+            .await
+            .unwrap();
+        insert_marker(pool, "uuid-fiji-east", -17.8, 178.0, "ship", None)
+            .await
+            .unwrap();
+        insert_marker(pool, "uuid-fiji-west", -16.5, -179.9, "ship", None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_markers_in_window_bbox() {
+        let pool = setup_test_db().await;
+        insert_bbox_fixtures(&pool).await;
+
+        let norway = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+        let (markers, max_id, _) = get_markers_in_window(&pool, DAY_MS, Some(&norway), None, 1000)
+            .await
+            .unwrap();
+        let uuids: Vec<&str> = markers.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(uuids, vec!["uuid-oslo", "uuid-bergen"]);
+        assert_eq!(max_id, 5); // max_id still covers the whole log
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_markers_in_window() {
+        let pool = setup_test_db().await;
+        let now = current_epoch_ms();
+        for (i, uuid) in ["uuid-1", "uuid-2", "uuid-3"].iter().enumerate() {
+            insert_marker_with_ts(
+                &pool,
+                uuid,
+                now - 3000 + i as i64 * 1000,
+                59.91,
+                10.75,
+                "marker",
+                None,
+            )
+            .await
+            .unwrap();
+        }
+        insert_marker(&pool, "uuid-bergen", 60.39, 5.32, "ship", None)
+            .await
+            .unwrap();
+
+        let oslo = BBox::parse("10,59,11,60").unwrap();
+        let (markers, has_more) = get_latest_markers_in_window(&pool, DAY_MS, &oslo, 2)
+            .await
+            .unwrap();
+        let uuids: Vec<&str> = markers.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(uuids, vec!["uuid-2", "uuid-3"]);
+        assert!(has_more);
+
+        let (markers, has_more) = get_latest_markers_in_window(&pool, DAY_MS, &oslo, 3)
+            .await
+            .unwrap();
+        assert_eq!(markers.len(), 3);
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_bbox_across_antimeridian() {
+        let pool = setup_test_db().await;
+        insert_bbox_fixtures(&pool).await;
+
+        let fiji = BBox::parse("175,-20,-178,-15").unwrap();
+        let (markers, _, _) = get_markers_in_window(&pool, DAY_MS, Some(&fiji), None, 1000)
+            .await
+            .unwrap();
+        let uuids: Vec<&str> = markers.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(uuids, vec!["uuid-fiji-east", "uuid-fiji-west"]);
+
+        let markers = get_markers_at(&pool, current_epoch_ms(), DAY_MS, Some(&fiji))
+            .await
+            .unwrap();
+        assert_eq!(markers.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_log_after_bbox() {
+        let pool = setup_test_db().await;
+        insert_bbox_fixtures(&pool).await;
+
+        let norway = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+
+        // Caught up: max_id jumps to the head, past non-matching entries
+        let (entries, max_id, has_more) =
+            get_log_after(&pool, 0, 100, Some(&norway)).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(max_id, 5);
+        assert!(!has_more);
+
+        // Paging within the box: max_id is the last returned entry
+        let (entries, max_id, has_more) = get_log_after(&pool, 0, 1, Some(&norway)).await.unwrap();
+        assert_eq!(entries[0].uuid, "uuid-oslo");
+        assert_eq!(max_id, 1);
+        assert!(has_more);
+
+        // Nothing matches after Bergen
+        let (entries, max_id, has_more) =
+            get_log_after(&pool, 2, 100, Some(&norway)).await.unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(max_id, 5);
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_rtree_kept_in_sync() {
+        let pool = setup_test_db().await;
+        insert_bbox_fixtures(&pool).await;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM marker_log_rtree")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 5);
+
+        // Re-running migrations must not duplicate index entries
+        run_migrations(&pool).await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM marker_log_rtree")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_indexes_backfill_existing_rows() {
+        let pool = init_pool("sqlite::memory:").await.unwrap();
+        sqlx::query(MIGRATIONS[0]).execute(&pool).await.unwrap();
+        insert_marker(&pool, "uuid-oslo", 59.91, 10.75, "marker", Some("Oslo"))
+            .await
+            .unwrap();
+
+        // Upgrading an existing database indexes the rows already logged
+        run_migrations(&pool).await.unwrap();
+
+        let norway = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+        let (markers, _, _) = get_markers_in_window(&pool, DAY_MS, Some(&norway), None, 1000)
+            .await
+            .unwrap();
+        assert_eq!(markers.len(), 1);
+
+        let (hits, _) = search_labels(&pool, "oslo", None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bbox_query_uses_rtree() {
+        let pool = setup_test_db().await;
+        let bbox = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+
+        let mut qb =
+            QueryBuilder::new("EXPLAIN QUERY PLAN SELECT id FROM marker_log WHERE ts_epoch_ms >= ");
+        qb.push_bind(0);
+        push_bbox_filter(&mut qb, &bbox, Some((0, DAY_MS)));
+        qb.push(" ORDER BY ts_epoch_ms ASC");
+
+        let plan: Vec<(i64, i64, i64, String)> =
+            qb.build_query_as().fetch_all(&pool).await.unwrap();
+        let details: Vec<&str> = plan.iter().map(|row| row.3.as_str()).collect();
+
+        // The index constrains lat (columns 1-2), lon (3-4) and time (5-6),
+        // so only markers from the window are visited
+        assert!(
+            details
+                .iter()
+                .any(|d| d.contains("marker_log_rtree") && d.contains("D5B4")),
+            "{:?}",
+            details
+        );
+        assert!(
+            !details.iter().any(|d| d.contains("ix_marker_log_ts")),
+            "{:?}",
+            details
+        );
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("oil slick"), r#""oil"* "slick"*"#);
+        assert_eq!(fts_query("  MMSI   257 "), r#""MMSI"* "257"*"#);
+        // FTS5 syntax is neutralised
+        assert_eq!(fts_query(r#"a" OR b"#), r#""a"""* "OR"* "b"*"#);
+        assert_eq!(fts_query("- * oil"), r#""oil"*"#);
+    }
+
+    #[test]
+    fn test_render_highlight() {
+        assert_eq!(
+            render_highlight("Big \u{2}oil\u{3} <slick>"),
+            "Big <mark>oil</mark> &lt;slick&gt;"
+        );
+        // Escaped control characters are literal text
+        assert_eq!(
+            render_highlight("\u{1}\u{4}\u{2}oil\u{3}\u{1}\u{5}\u{1}\u{1}"),
+            "\u{2}<mark>oil</mark>\u{3}\u{1}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_labels() {
+        let pool = setup_test_db().await;
+
+        let now = current_epoch_ms();
+        let two_days_ago = now - (48 * 60 * 60 * 1000);
+
+        insert_marker_with_ts(
+            &pool,
+            "uuid-old",
+            two_days_ago,
+            59.91,
+            10.75,
+            "marker",
+            Some("Oil slick near Oslo"),
+        )
+        .await
+        .unwrap();
+        insert_marker(
+            &pool,
+            "uuid-ship",
+            60.39,
+            5.32,
+            "ship",
+            Some("MMSI 257123456"),
+        )
+        .await
+        .unwrap();
+        insert_marker(&pool, "uuid-oil", 63.43, 10.39, "ship", Some("oil"))
+            .await
+            .unwrap();
+        insert_marker(&pool, "uuid-none", 63.43, 10.39, "ship", None)
+            .await
+            .unwrap();
+
+        // Matches across the whole log, not just the last 24 hours
+        let (hits, has_more) = search_labels(&pool, "oil", None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(!has_more);
+        // The shorter label is the better match
+        assert_eq!(hits[0].marker.uuid, "uuid-oil");
+        assert!(hits[0].score >= hits[1].score);
+        assert_eq!(hits[1].highlight, "<mark>Oil</mark> slick near Oslo");
+
+        // Prefix match on numbers
+        let (hits, _) = search_labels(&pool, "mmsi 257", None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].marker.uuid, "uuid-ship");
+
+        // Time bounds
+        let (hits, _) = search_labels(&pool, "oil", Some(now - 1000), None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].marker.uuid, "uuid-oil");
+
+        // Paging
+        let (hits, has_more) = search_labels(&pool, "oil", None, None, 1, 0).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(has_more);
+        let (hits, has_more) = search_labels(&pool, "oil", None, None, 1, 1).await.unwrap();
+        assert_eq!(hits[0].marker.uuid, "uuid-old");
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_search_labels_with_control_chars() {
+        let pool = setup_test_db().await;
+
+        // Labels may hold anything, including the highlight markers
+        let label = "a\u{2}b\u{3}\u{1}oil\u{3}\nslick";
+        insert_marker(&pool, "uuid-ctl", 59.91, 10.75, "ship", Some(label))
+            .await
+            .unwrap();
+
+        let (hits, _) = search_labels(&pool, "oil", None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].marker.label.as_deref(), Some(label));
+        assert_eq!(
+            hits[0].highlight,
+            "a\u{2}b\u{3}\u{1}<mark>oil</mark>\u{3}\nslick"
+        );
+
+        // Tokens either side of a control character are still separate words
+        let (hits, _) = search_labels(&pool, "b", None, None, 10, 0).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].highlight,
+            "a\u{2}<mark>b</mark>\u{3}\u{1}oil\u{3}\nslick"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_markers_range_pagination() {
+        let pool = setup_test_db().await;
+
+        // Two markers share a timestamp, so paging must tie-break on id
+        let base = 1705665600000;
+        for (i, ts) in [base, base + 1000, base + 1000, base + 2000, base + 5000]
+            .iter()
+            .enumerate()
+        {
+            insert_marker_with_ts(
+                &pool,
+                &format!("uuid-{}", i + 1),
+                *ts,
+                59.91,
+                10.75,
+                "marker",
+                None,
+            )
+            .await
+            .unwrap();
+        }
+
+        // Range excludes the last marker
+        let (page, has_more) = get_markers_range(&pool, base, base + 2000, None, 2, None)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 2);
+        assert!(has_more);
+        assert_eq!(page[1].uuid, "uuid-2");
+
+        let cursor = MarkerCursor::after(&page[1]);
+        let (page, has_more) = get_markers_range(&pool, base, base + 2000, Some(cursor), 2, None)
+            .await
+            .unwrap();
+        let uuids: Vec<&str> = page.iter().map(|m| m.uuid.as_str()).collect();
+        assert_eq!(uuids, vec!["uuid-3", "uuid-4"]);
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_insert_marker_in_transaction() {
+        let pool = setup_test_db().await;
+
+        let mut tx = pool.begin().await.unwrap();
+        let (first, created) = insert_marker_in(&mut tx, "uuid-1", 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+        assert!(created);
+        // A repeat in the same transaction sees the first insert
+        let (repeat, created) = insert_marker_in(&mut tx, "uuid-1", 0.0, 0.0, "marker", None)
+            .await
+            .unwrap();
+        assert!(!created);
+        assert_eq!(repeat, first);
+        tx.rollback().await.unwrap();
+
+        let (entries, max_id, _) = get_log_after(&pool, 0, 100, None).await.unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(max_id, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_markers_range() {
+        let pool = setup_test_db().await;
+
+        let base = 1705665600000;
+        let rows = [
+            ("uuid-1", base + 2000, 59.91, 10.75, "marker"),
+            ("uuid-2", base, 59.91, 10.75, "ship"),
+            ("uuid-3", base + 1000, 60.39, 5.32, "marker"),
+            ("uuid-4", base + 9000, 59.91, 10.75, "marker"),
+        ];
+        for (uuid, ts, lat, lon, icon_id) in rows {
+            insert_marker_with_ts(&pool, uuid, ts, lat, lon, icon_id, None)
+                .await
+                .unwrap();
+        }
+
+        let uuids =
+            |markers: Vec<Marker>| -> Vec<String> { markers.into_iter().map(|m| m.uuid).collect() };
+
+        // Ordered by timestamp, not id
+        let all: Vec<Marker> = stream_markers_range(pool.clone(), base, base + 5000, None, None)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(uuids(all), vec!["uuid-2", "uuid-3", "uuid-1"]);
+
+        let oslo = BBox::parse("10,59,11,60").unwrap();
+        let filtered: Vec<Marker> = stream_markers_range(
+            pool.clone(),
+            base,
+            base + 5000,
+            Some("marker".to_string()),
+            Some(oslo),
+        )
+        .try_collect()
+        .await
+        .unwrap();
+        assert_eq!(uuids(filtered), vec!["uuid-1"]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_log_after() {
+        let pool = setup_test_db().await;
+
+        for i in 1..=5 {
+            insert_marker(&pool, &format!("uuid-{}", i), 59.91, 10.75, "marker", None)
+                .await
+                .unwrap();
+        }
+
+        let entries: Vec<Marker> = stream_log_after(pool.clone(), 2)
+            .try_collect()
+            .await
+            .unwrap();
+        let ids: Vec<i64> = entries.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![3, 4, 5]);
     }
 
     #[tokio::test]
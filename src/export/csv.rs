@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use crate::export::iso8601;
+use crate::models::Marker;
+
+/// Media type for CSV (RFC 4180).
+pub const CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// Header record, ending in CRLF like every record.
+pub const HEADER: &str = "id,uuid,ts_epoch_ms,timestamp,lat,lon,icon_id,label\r\n";
+
+/// A marker as one CSV record, ending in CRLF.
+pub fn record(marker: &Marker) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{}\r\n",
+        marker.id,
+        field(&marker.uuid),
+        marker.ts_epoch_ms,
+        iso8601(marker.ts_epoch_ms),
+        marker.lat,
+        marker.lon,
+        field(&marker.icon_id),
+        field(marker.label.as_deref().unwrap_or("")),
+    )
+}
+
+/// Quote a field if it contains a delimiter, quote or line break, doubling any
+/// quotes inside.
+fn field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(label: Option<&str>) -> Marker {
+        Marker {
+            id: 7,
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "marker".to_string(),
+            label: label.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        assert_eq!(
+            record(&marker(Some("Oslo"))),
+            "7,550e8400-e29b-41d4-a716-446655440000,1705665600000,2024-01-19T12:00:00.000Z,59.91,10.75,marker,Oslo\r\n"
+        );
+        assert!(record(&marker(None)).ends_with(",marker,\r\n"));
+    }
+
+    #[test]
+    fn test_record_quotes_label() {
+        assert!(record(&marker(Some("Harbour, \"east\" pier")))
+            .ends_with(",\"Harbour, \"\"east\"\" pier\"\r\n"));
+        assert!(record(&marker(Some("two\nlines"))).ends_with(",\"two\nlines\"\r\n"));
+    }
+}
@@ -0,0 +1,75 @@
+use serde_json::{json, Map, Value};
+
+use crate::models::Marker;
+
+/// Media type for GeoJSON (RFC 7946).
+pub const CONTENT_TYPE: &str = "application/geo+json";
+
+/// A marker as a GeoJSON Point feature. Every other marker field is a property.
+pub fn feature(marker: &Marker) -> Value {
+    json!({
+        "type": "Feature",
+        "id": marker.id,
+        "geometry": {
+            "type": "Point",
+            "coordinates": [marker.lon, marker.lat],
+        },
+        "properties": {
+            "id": marker.id,
+            "uuid": marker.uuid,
+            "ts_epoch_ms": marker.ts_epoch_ms,
+            "icon_id": marker.icon_id,
+            "label": marker.label,
+        },
+    })
+}
+
+/// A FeatureCollection of markers. `members` (response metadata such as
+/// `max_id`) are kept as foreign members next to `features`.
+pub fn feature_collection(markers: &[Marker], members: Map<String, Value>) -> Value {
+    let mut collection = members;
+    collection.insert("type".to_string(), json!("FeatureCollection"));
+    collection.insert(
+        "features".to_string(),
+        Value::Array(markers.iter().map(feature).collect()),
+    );
+    Value::Object(collection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker() -> Marker {
+        Marker {
+            id: 7,
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "marker".to_string(),
+            label: Some("Oslo".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_feature_point_is_lon_lat() {
+        let feature = feature(&marker());
+        assert_eq!(feature["type"], "Feature");
+        assert_eq!(feature["geometry"]["type"], "Point");
+        assert_eq!(feature["geometry"]["coordinates"], json!([10.75, 59.91]));
+        assert_eq!(feature["properties"]["uuid"], marker().uuid);
+        assert_eq!(feature["properties"]["label"], "Oslo");
+    }
+
+    #[test]
+    fn test_feature_collection_keeps_members() {
+        let mut members = Map::new();
+        members.insert("max_id".to_string(), json!(7));
+
+        let collection = feature_collection(&[marker()], members);
+        assert_eq!(collection["type"], "FeatureCollection");
+        assert_eq!(collection["max_id"], 7);
+        assert_eq!(collection["features"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::export::{iso8601, xml_escape};
+use crate::models::Marker;
+
+/// Media type for GPX.
+pub const CONTENT_TYPE: &str = "application/gpx+xml";
+
+/// Opening of a GPX 1.1 document.
+pub const HEADER: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<gpx version=\"1.1\" creator=\"fylge\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+);
+
+/// Closes the document opened by `HEADER`.
+pub const FOOTER: &str = "</gpx>\n";
+
+/// A marker as a waypoint: label as `name` (omitted if unset) and icon as `sym`.
+/// Child elements are in the order the GPX schema requires.
+pub fn waypoint(marker: &Marker) -> String {
+    let name = marker
+        .label
+        .as_deref()
+        .map(|label| format!("<name>{}</name>", xml_escape(label)))
+        .unwrap_or_default();
+    format!(
+        "<wpt lat=\"{}\" lon=\"{}\"><time>{}</time>{}<sym>{}</sym></wpt>\n",
+        marker.lat,
+        marker.lon,
+        iso8601(marker.ts_epoch_ms),
+        name,
+        xml_escape(&marker.icon_id),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waypoint() {
+        let mut marker = Marker {
+            id: 7,
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "ship".to_string(),
+            label: Some("Buoy <3>".to_string()),
+        };
+        assert_eq!(
+            waypoint(&marker),
+            "<wpt lat=\"59.91\" lon=\"10.75\"><time>2024-01-19T12:00:00.000Z</time><name>Buoy &lt;3&gt;</name><sym>ship</sym></wpt>\n"
+        );
+
+        marker.label = None;
+        assert!(!waypoint(&marker).contains("<name>"));
+    }
+}
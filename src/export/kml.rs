@@ -0,0 +1,116 @@
+use crate::export::{iso8601, xml_escape};
+use crate::models::{Icon, Marker};
+
+/// Media type for KML.
+pub const CONTENT_TYPE: &str = "application/vnd.google-earth.kml+xml";
+
+/// Closes the document opened by `header`.
+pub const FOOTER: &str = "</Document>\n</kml>\n";
+
+/// Opening of a KML document with one shared `Style` per icon. Relative icon
+/// URLs are made absolute against `base_url` (e.g. `https://fylge.example`),
+/// since KML files are opened away from the server; an empty `base_url` keeps
+/// them root-relative.
+pub fn header(icons: &[Icon], base_url: &str) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n",
+        "<Document>\n",
+        "<name>fylge markers</name>\n",
+    ));
+    for icon in icons {
+        out.push_str(&format!(
+            "<Style id=\"{}\"><IconStyle><Icon><href>{}</href></Icon></IconStyle></Style>\n",
+            xml_escape(&style_id(&icon.id)),
+            xml_escape(&absolute_url(&icon.url, base_url)),
+        ));
+    }
+    out
+}
+
+/// A marker as a `Placemark` using its icon's style. The label is the name;
+/// `uuid` and `icon_id` are kept as extended data.
+pub fn placemark(marker: &Marker) -> String {
+    format!(
+        concat!(
+            "<Placemark id=\"marker-{}\">",
+            "<name>{}</name>",
+            "<styleUrl>#{}</styleUrl>",
+            "<TimeStamp><when>{}</when></TimeStamp>",
+            "<ExtendedData>",
+            "<Data name=\"uuid\"><value>{}</value></Data>",
+            "<Data name=\"icon_id\"><value>{}</value></Data>",
+            "</ExtendedData>",
+            "<Point><coordinates>{},{}</coordinates></Point>",
+            "</Placemark>\n",
+        ),
+        marker.id,
+        xml_escape(marker.label.as_deref().unwrap_or("")),
+        xml_escape(&style_id(&marker.icon_id)),
+        iso8601(marker.ts_epoch_ms),
+        xml_escape(&marker.uuid),
+        xml_escape(&marker.icon_id),
+        marker.lon,
+        marker.lat,
+    )
+}
+
+fn style_id(icon_id: &str) -> String {
+    format!("icon-{}", icon_id)
+}
+
+fn absolute_url(url: &str, base_url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else {
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            url.trim_start_matches('/')
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_styles_use_absolute_urls() {
+        let icons = vec![
+            Icon {
+                id: "ship".to_string(),
+                name: "Ship".to_string(),
+                url: "/static/icons/ship.svg".to_string(),
+            },
+            Icon {
+                id: "cdn".to_string(),
+                name: "CDN".to_string(),
+                url: "https://cdn.example/cdn.png".to_string(),
+            },
+        ];
+        let header = header(&icons, "https://fylge.example/");
+        assert!(header.contains(
+            "<Style id=\"icon-ship\"><IconStyle><Icon><href>https://fylge.example/static/icons/ship.svg</href>"
+        ));
+        assert!(header.contains("<href>https://cdn.example/cdn.png</href>"));
+    }
+
+    #[test]
+    fn test_placemark() {
+        let marker = Marker {
+            id: 7,
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "ship".to_string(),
+            label: Some("Fish & chips".to_string()),
+        };
+        let placemark = placemark(&marker);
+        assert!(placemark.starts_with("<Placemark id=\"marker-7\"><name>Fish &amp; chips</name>"));
+        assert!(placemark.contains("<styleUrl>#icon-ship</styleUrl>"));
+        assert!(placemark.contains("<when>2024-01-19T12:00:00.000Z</when>"));
+        assert!(placemark.contains("<coordinates>10.75,59.91</coordinates>"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Mapbox Vector Tile (MVT 2.1) encoding of markers as point features.
+//!
+//! The protobuf wire format is written directly; a tile of points only needs a
+//! handful of fields.
+
+use std::collections::HashMap;
+
+use crate::geo::web_mercator;
+use crate::models::{Marker, TileCoord};
+
+/// Media type for Mapbox Vector Tiles.
+pub const CONTENT_TYPE: &str = "application/vnd.mapbox-vector-tile";
+
+/// Name of the layer holding the markers.
+pub const LAYER: &str = "markers";
+
+/// Tile coordinate space, in units per tile side.
+pub const EXTENT: u32 = 4096;
+
+// Protobuf wire types
+const VARINT: u32 = 0;
+const LEN: u32 = 2;
+
+// Feature geometry
+const POINT: u64 = 1;
+const MOVE_TO: u32 = 1;
+
+/// Attribute value; only strings and integers are needed.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum AttrValue {
+    String(String),
+    Int(i64),
+}
+
+/// Encode markers as a tile with a single `markers` layer. Each marker is a
+/// point feature with its log `id` and `uuid`, `icon_id`, `ts_epoch_ms` and
+/// (when set) `label` attributes.
+pub fn encode(tile: TileCoord, markers: &[Marker]) -> Vec<u8> {
+    let keys = ["uuid", "icon_id", "ts_epoch_ms", "label"];
+    let mut values: Vec<AttrValue> = Vec::new();
+    let mut value_index: HashMap<AttrValue, u32> = HashMap::new();
+    let mut intern = |value: AttrValue| -> u32 {
+        *value_index.entry(value.clone()).or_insert_with(|| {
+            values.push(value);
+            values.len() as u32 - 1
+        })
+    };
+
+    let mut layer = Vec::new();
+    field_varint(&mut layer, 15, 2); // version
+    field_bytes(&mut layer, 1, LAYER.as_bytes());
+
+    let n = (1u64 << tile.z) as f64;
+    for marker in markers {
+        let mut tags = vec![
+            0,
+            intern(AttrValue::String(marker.uuid.clone())),
+            1,
+            intern(AttrValue::String(marker.icon_id.clone())),
+            2,
+            intern(AttrValue::Int(marker.ts_epoch_ms)),
+        ];
+        if let Some(label) = &marker.label {
+            tags.extend([3, intern(AttrValue::String(label.clone()))]);
+        }
+
+        // Position within the tile, in extent units from its top-left corner
+        let (wx, wy) = web_mercator(marker.lat, marker.lon);
+        let px = ((wx * n - tile.x as f64) * EXTENT as f64).round() as i32;
+        let py = ((wy * n - tile.y as f64) * EXTENT as f64).round() as i32;
+        let geometry = [command(MOVE_TO, 1), zigzag(px), zigzag(py)];
+
+        let mut feature = Vec::new();
+        field_varint(&mut feature, 1, marker.id as u64);
+        field_packed(&mut feature, 2, &tags);
+        field_varint(&mut feature, 3, POINT);
+        field_packed(&mut feature, 4, &geometry);
+        field_bytes(&mut layer, 2, &feature);
+    }
+
+    for key in keys {
+        field_bytes(&mut layer, 3, key.as_bytes());
+    }
+    for value in &values {
+        let mut encoded = Vec::new();
+        match value {
+            AttrValue::String(s) => field_bytes(&mut encoded, 1, s.as_bytes()),
+            AttrValue::Int(i) => field_varint(&mut encoded, 4, *i as u64),
+        }
+        field_bytes(&mut layer, 4, &encoded);
+    }
+    field_varint(&mut layer, 5, EXTENT as u64);
+
+    let mut out = Vec::new();
+    field_bytes(&mut out, 3, &layer);
+    out
+}
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+fn zigzag(n: i32) -> u32 {
+    ((n << 1) ^ (n >> 31)) as u32
+}
+
+fn varint(buf: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        buf.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    buf.push(n as u8);
+}
+
+fn field_varint(buf: &mut Vec<u8>, field: u32, n: u64) {
+    varint(buf, ((field << 3) | VARINT) as u64);
+    varint(buf, n);
+}
+
+fn field_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    varint(buf, ((field << 3) | LEN) as u64);
+    varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn field_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::new();
+    for value in values {
+        varint(&mut packed, *value as u64);
+    }
+    field_bytes(buf, field, &packed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(id: i64, label: Option<&str>) -> Marker {
+        Marker {
+            id,
+            uuid: format!("uuid-{}", id),
+            ts_epoch_ms: 1705665600000,
+            lat: 0.0,
+            lon: 0.0,
+            icon_id: "ship".to_string(),
+            label: label.map(str::to_string),
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn test_varint_and_zigzag() {
+        let mut buf = Vec::new();
+        varint(&mut buf, 300);
+        assert_eq!(buf, vec![0xac, 0x02]);
+        assert_eq!(zigzag(0), 0);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+        assert_eq!(command(MOVE_TO, 1), 9);
+    }
+
+    #[test]
+    fn test_encode_point_in_world_tile() {
+        let tile = TileCoord { z: 0, x: 0, y: 0 };
+        let bytes = encode(tile, &[marker(1, Some("Null Island")), marker(2, None)]);
+
+        // Tile -> layer
+        assert_eq!(bytes[0], 3 << 3 | 2);
+        assert!(contains(&bytes, LAYER.as_bytes()));
+        assert!(contains(&bytes, b"Null Island"));
+        // (0, 0) is the tile centre: MoveTo(2048, 2048), zigzagged
+        assert!(contains(
+            &bytes,
+            &[4 << 3 | 2, 5, 9, 0x80, 0x20, 0x80, 0x20]
+        ));
+        // Shared values are stored once
+        assert_eq!(bytes.windows(4).filter(|w| w == b"ship").count(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::models::BBox;
+
+/// Mean Earth radius in kilometres.
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+/// Great-circle distance between two points in kilometres (haversine formula).
+pub fn distance_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let dphi = (lat2 - lat1).to_radians();
+    let dlambda = (lon2 - lon1).to_radians();
+
+    let a = (dphi / 2.0).sin().powi(2) + phi1.cos() * phi2.cos() * (dlambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+/// Initial bearing from point 1 to point 2 in degrees clockwise from north (0..360).
+pub fn bearing_deg(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let dlambda = (lon2 - lon1).to_radians();
+
+    let y = dlambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * dlambda.cos();
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Smallest lat/lon box containing every point within `radius_km` of a centre.
+/// Used to prefilter candidates before computing exact distances.
+pub fn bbox_around(lat: f64, lon: f64, radius_km: f64) -> BBox {
+    let angular = radius_km / EARTH_RADIUS_KM;
+    let dlat = angular.to_degrees();
+    let min_lat = lat - dlat;
+    let max_lat = lat + dlat;
+
+    // A circle reaching a pole covers every longitude
+    if min_lat <= -90.0 || max_lat >= 90.0 {
+        return BBox {
+            min_lon: -180.0,
+            min_lat: min_lat.max(-90.0),
+            max_lon: 180.0,
+            max_lat: max_lat.min(90.0),
+        };
+    }
+
+    // Past a quarter circumference, or once the circle's longitude span
+    // saturates, every longitude is within reach
+    let ratio = angular.sin() / lat.to_radians().cos();
+    if angular >= std::f64::consts::FRAC_PI_2 || ratio >= 1.0 {
+        return BBox {
+            min_lon: -180.0,
+            min_lat,
+            max_lon: 180.0,
+            max_lat,
+        };
+    }
+    let dlon = ratio.asin().to_degrees();
+
+    // Wrap into [-180, 180]; a box that wraps ends up with min_lon > max_lon
+    let wrap = |l: f64| {
+        if l < -180.0 {
+            l + 360.0
+        } else if l > 180.0 {
+            l - 360.0
+        } else {
+            l
+        }
+    };
+
+    BBox {
+        min_lon: wrap(lon - dlon),
+        min_lat,
+        max_lon: wrap(lon + dlon),
+        max_lat,
+    }
+}
+
+/// Largest latitude shown by Web Mercator, where the square world map ends.
+pub const MAX_MERCATOR_LAT: f64 = 85.051_128_779_806_59;
+
+/// Project to Web Mercator world coordinates in 0..1, measured east and south
+/// from the top-left corner of the map.
+pub fn web_mercator(lat: f64, lon: f64) -> (f64, f64) {
+    let x = (lon + 180.0) / 360.0;
+    let phi = lat.clamp(-MAX_MERCATOR_LAT, MAX_MERCATOR_LAT).to_radians();
+    let y = (1.0 - (phi.tan() + 1.0 / phi.cos()).ln() / std::f64::consts::PI) / 2.0;
+    (x, y)
+}
+
+/// Bounding box of Web Mercator tile `z/x/y`. The top and bottom rows reach the
+/// poles, so markers beyond `MAX_MERCATOR_LAT` land on the map's edge.
+pub fn tile_bbox(z: u32, x: u32, y: u32) -> BBox {
+    let n = (1u64 << z) as f64;
+    let lon = |x: f64| x / n * 360.0 - 180.0;
+    let lat = |y: f64| {
+        (std::f64::consts::PI * (1.0 - 2.0 * y / n))
+            .sinh()
+            .atan()
+            .to_degrees()
+    };
+    let (x, y) = (x as f64, y as f64);
+
+    BBox {
+        min_lon: lon(x),
+        min_lat: if y + 1.0 >= n { -90.0 } else { lat(y + 1.0) },
+        max_lon: lon(x + 1.0),
+        max_lat: if y == 0.0 { 90.0 } else { lat(y) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_oslo_bergen() {
+        let d = distance_km(59.91, 10.75, 60.39, 5.32);
+        assert!((d - 305.0).abs() < 2.0, "{}", d);
+    }
+
+    #[test]
+    fn test_distance_zero() {
+        assert_eq!(distance_km(59.91, 10.75, 59.91, 10.75), 0.0);
+    }
+
+    #[test]
+    fn test_distance_across_antimeridian() {
+        // One degree of longitude at the equator, across 180
+        let d = distance_km(0.0, 179.5, 0.0, -179.5);
+        assert!((d - 111.2).abs() < 0.5, "{}", d);
+    }
+
+    #[test]
+    fn test_bearing_cardinal_directions() {
+        assert!((bearing_deg(0.0, 0.0, 1.0, 0.0) - 0.0).abs() < 1e-9);
+        assert!((bearing_deg(0.0, 0.0, 0.0, 1.0) - 90.0).abs() < 1e-9);
+        assert!((bearing_deg(0.0, 0.0, -1.0, 0.0) - 180.0).abs() < 1e-9);
+        assert!((bearing_deg(0.0, 0.0, 0.0, -1.0) - 270.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bbox_around_contains_circle() {
+        let bbox = bbox_around(59.91, 10.75, 20.0);
+        assert!(!bbox.crosses_antimeridian());
+        assert!(bbox.contains(59.91 + 0.17, 10.75));
+        assert!(bbox.contains(59.91, 10.75 + 0.35));
+        assert!(!bbox.contains(59.91 + 0.2, 10.75));
+    }
+
+    #[test]
+    fn test_bbox_around_antimeridian() {
+        let bbox = bbox_around(-17.8, 179.9, 50.0);
+        assert!(bbox.crosses_antimeridian());
+        assert!(bbox.contains(-17.8, -179.9));
+    }
+
+    #[test]
+    fn test_bbox_around_large_radius() {
+        let bbox = bbox_around(0.0, 0.0, 15_000.0);
+        assert_eq!(bbox.min_lon, -180.0);
+        assert_eq!(bbox.max_lon, 180.0);
+    }
+
+    #[test]
+    fn test_bbox_around_pole() {
+        let bbox = bbox_around(89.9, 0.0, 50.0);
+        assert_eq!(bbox.min_lon, -180.0);
+        assert_eq!(bbox.max_lon, 180.0);
+        assert_eq!(bbox.max_lat, 90.0);
+    }
+
+    #[test]
+    fn test_web_mercator() {
+        let (x, y) = web_mercator(0.0, 0.0);
+        assert!((x - 0.5).abs() < 1e-12 && (y - 0.5).abs() < 1e-12);
+        let (x, y) = web_mercator(90.0, -180.0);
+        assert!(x.abs() < 1e-12 && y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tile_bbox() {
+        let world = tile_bbox(0, 0, 0);
+        assert_eq!((world.min_lon, world.max_lon), (-180.0, 180.0));
+        assert_eq!((world.min_lat, world.max_lat), (-90.0, 90.0));
+
+        // Oslo is in 10/542/297
+        let tile = tile_bbox(10, 542, 297);
+        assert!(tile.contains(59.91, 10.75));
+        assert!((tile.max_lat - tile.min_lat) < 0.2);
+    }
+}
@@ -1,12 +1,16 @@
+pub mod bus;
 pub mod config;
 pub mod db;
+pub mod export;
+pub mod geo;
 pub mod models;
 pub mod routes;
 pub mod state;
 
+pub use bus::{ChangeBus, LogEvent, LogSubscription};
 pub use config::Config;
 pub use db::{current_epoch_ms, init_pool, run_migrations};
-pub use models::{ApiError, CreateMarkerRequest, Icon, Marker, ValidationError};
+pub use models::{ApiError, BBox, CreateMarkerRequest, Icon, Marker, ValidationError};
 pub use routes::api::load_icons;
 pub use routes::create_router;
 pub use state::AppState;
@@ -17,6 +17,10 @@ async fn main() {
             eprintln!("Configuration error: {}", e);
             eprintln!("Optional: DATABASE_URL (default: sqlite://fylge.db)");
             eprintln!("Optional: LISTEN_ADDR (default: 0.0.0.0:3000)");
+            eprintln!("Optional: WINDOW_HOURS (default: 24)");
+            eprintln!("Optional: MAX_WINDOW_HOURS (default: 168)");
+            eprintln!("Optional: MAX_MARKERS_PAGE (default: 10000)");
+            eprintln!("Optional: PUBLIC_URL (e.g. https://fylge.example)");
             std::process::exit(1);
         }
     };
@@ -24,6 +28,11 @@ async fn main() {
     tracing::info!("Starting Fylge server");
     tracing::info!("Listen address: {}", config.listen_addr);
     tracing::info!("Database: {}", config.database_url);
+    tracing::info!(
+        "Visibility window: {}h (max {}h)",
+        config.window_ms / 3_600_000,
+        config.max_window_ms / 3_600_000
+    );
 
     // Connect to database
     let pool = match init_pool(&config.database_url).await {
@@ -46,7 +55,16 @@ async fn main() {
     tracing::info!("Loaded {} icons", icons.len());
 
     // Create app state
-    let state = AppState::new(pool, icons);
+    let mut state = AppState::new(pool, icons)
+        .with_window(config.window_ms, config.max_window_ms)
+        .with_max_markers_page(config.max_markers_page);
+    match &config.public_url {
+        Some(public_url) => {
+            tracing::info!("Public URL: {}", public_url);
+            state = state.with_public_url(public_url);
+        }
+        None => tracing::warn!("PUBLIC_URL is not set; icon links in KML exports are relative"),
+    }
 
     // Build router
     let app = create_router(state).nest_service("/static", ServeDir::new("static"));
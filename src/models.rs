@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 /// Validation error type.
 #[derive(Debug, Clone, PartialEq)]
@@ -12,7 +12,27 @@ pub enum ValidationError {
     IconIdNotFound(String),
     LabelTooLong(usize),
     InvalidLimit(i64),
+    InvalidPageLimit(i64),
     InvalidTimestamp(String),
+    InvalidLastEventId(String),
+    InvalidWaitMs(i64),
+    InvalidBbox(String),
+    InvalidRadius(f64),
+    InvalidQuery(String),
+    InvalidTimeRange(i64, i64),
+    InvalidOffset(i64),
+    InvalidCursor(String),
+    InvalidWindow(i64, i64),
+    InvalidZoom(i64),
+    BboxRequired(i64),
+    InvalidBucketMs(i64),
+    InvalidCellDeg(f64),
+    InvalidHalfLife(i64),
+    TooManyUuids(usize),
+    InvalidBeforeId(i64),
+    InvalidFormat(String),
+    InvalidTile(String),
+    TooManyMarkers(usize),
 }
 
 impl std::fmt::Display for ValidationError {
@@ -42,9 +62,131 @@ impl std::fmt::Display for ValidationError {
             ValidationError::InvalidLimit(limit) => {
                 write!(f, "Invalid limit: {} (must be between 1 and 1000)", limit)
             }
+            ValidationError::InvalidPageLimit(limit) => write!(
+                f,
+                "Invalid limit: {} (must be at least 1; larger values are capped at the page size)",
+                limit
+            ),
             ValidationError::InvalidTimestamp(s) => {
                 write!(f, "Invalid timestamp: {} (must be epoch milliseconds)", s)
             }
+            ValidationError::InvalidLastEventId(s) => {
+                write!(f, "Invalid Last-Event-ID: {} (must be a log id)", s)
+            }
+            ValidationError::InvalidBbox(s) => {
+                write!(
+                    f,
+                    "Invalid bbox: {} (must be min_lon,min_lat,max_lon,max_lat)",
+                    s
+                )
+            }
+            ValidationError::InvalidRadius(radius_km) => {
+                write!(
+                    f,
+                    "Invalid radius_km: {} (must be greater than 0 and at most {})",
+                    radius_km, MAX_RADIUS_KM
+                )
+            }
+            ValidationError::InvalidQuery(q) => {
+                write!(
+                    f,
+                    "Invalid search query: '{}' (must contain a word, max 256 chars)",
+                    q
+                )
+            }
+            ValidationError::InvalidTimeRange(from, to) => {
+                write!(
+                    f,
+                    "Invalid time range: from {} to {} (must be epoch milliseconds with from <= to)",
+                    from, to
+                )
+            }
+            ValidationError::InvalidOffset(offset) => {
+                write!(
+                    f,
+                    "Invalid offset: {} (must be between 0 and {})",
+                    offset, MAX_SEARCH_OFFSET
+                )
+            }
+            ValidationError::InvalidWindow(window_ms, max) => {
+                write!(
+                    f,
+                    "Invalid window_ms: {} (must be between 1 and {})",
+                    window_ms, max
+                )
+            }
+            ValidationError::InvalidZoom(zoom) => {
+                write!(
+                    f,
+                    "Invalid zoom: {} (must be between 0 and {})",
+                    zoom, MAX_CLUSTER_ZOOM
+                )
+            }
+            ValidationError::BboxRequired(zoom) => {
+                write!(
+                    f,
+                    "bbox is required at zoom {} (from zoom {} markers are returned individually)",
+                    zoom, EXPAND_CLUSTER_ZOOM
+                )
+            }
+            ValidationError::InvalidBucketMs(bucket_ms) => {
+                write!(
+                    f,
+                    "Invalid bucket_ms: {} (must be positive and give at most {} buckets)",
+                    bucket_ms, MAX_HISTOGRAM_BUCKETS
+                )
+            }
+            ValidationError::InvalidCellDeg(cell_deg) => {
+                write!(
+                    f,
+                    "Invalid cell_deg: {} (must be between {} and {}, and give at most {} cells)",
+                    cell_deg, MIN_HEATMAP_CELL_DEG, MAX_HEATMAP_CELL_DEG, MAX_HEATMAP_CELLS
+                )
+            }
+            ValidationError::InvalidHalfLife(half_life_ms) => {
+                write!(
+                    f,
+                    "Invalid half_life_ms: {} (must be positive)",
+                    half_life_ms
+                )
+            }
+            ValidationError::TooManyUuids(count) => {
+                write!(f, "Too many uuids: {} (max {})", count, MAX_LOOKUP_UUIDS)
+            }
+            ValidationError::TooManyMarkers(count) => {
+                write!(f, "Too many markers: {} (max {})", count, MAX_BATCH_MARKERS)
+            }
+            ValidationError::InvalidBeforeId(before_id) => {
+                write!(
+                    f,
+                    "Invalid before_id: {} (must be >= 0, and cannot be combined with after_id or wait_ms)",
+                    before_id
+                )
+            }
+            ValidationError::InvalidFormat(format) => {
+                write!(
+                    f,
+                    "Invalid format: {} (must be json, geojson, msgpack or cbor)",
+                    format
+                )
+            }
+            ValidationError::InvalidTile(tile) => {
+                write!(
+                    f,
+                    "Invalid tile: {} (must be z/x/y.mvt with z <= {} and x, y < 2^z)",
+                    tile, MAX_TILE_ZOOM
+                )
+            }
+            ValidationError::InvalidCursor(s) => {
+                write!(f, "Invalid cursor: {} (use next_cursor from a response)", s)
+            }
+            ValidationError::InvalidWaitMs(wait_ms) => {
+                write!(
+                    f,
+                    "Invalid wait_ms: {} (must be between 0 and {})",
+                    wait_ms, MAX_WAIT_MS
+                )
+            }
         }
     }
 }
@@ -125,6 +267,70 @@ impl CreateMarkerRequest {
     }
 }
 
+/// Geographic bounding box, parsed from `min_lon,min_lat,max_lon,max_lat`.
+/// A box with `min_lon > max_lon` crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BBox {
+    /// Parse and validate a `min_lon,min_lat,max_lon,max_lat` string.
+    pub fn parse(s: &str) -> Result<Self, ValidationError> {
+        let invalid = || ValidationError::InvalidBbox(s.to_string());
+
+        let values: Vec<f64> = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            return Err(invalid());
+        };
+
+        if !(-180.0..=180.0).contains(&min_lon) || !(-180.0..=180.0).contains(&max_lon) {
+            return Err(invalid());
+        }
+        if !(-90.0..=90.0).contains(&min_lat) || !(-90.0..=90.0).contains(&max_lat) {
+            return Err(invalid());
+        }
+        if min_lat > max_lat {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+
+    /// True if the box wraps around from +180 to -180 longitude.
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lon > self.max_lon
+    }
+
+    /// Check whether a point lies inside the box (edges inclusive).
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        if lat < self.min_lat || lat > self.max_lat {
+            return false;
+        }
+        if self.crosses_antimeridian() {
+            lon >= self.min_lon || lon <= self.max_lon
+        } else {
+            lon >= self.min_lon && lon <= self.max_lon
+        }
+    }
+}
+
+/// Parse an optional `bbox` query parameter.
+fn parse_bbox(bbox: &Option<String>) -> Result<Option<BBox>, ValidationError> {
+    bbox.as_deref().map(BBox::parse).transpose()
+}
+
 /// Response for creating a marker.
 #[derive(Debug, Serialize)]
 pub struct CreateMarkerResponse {
@@ -132,20 +338,221 @@ pub struct CreateMarkerResponse {
     pub marker: Marker,
 }
 
-/// Response for getting markers (last 24h).
+/// Most markers accepted in one batch create request.
+pub const MAX_BATCH_MARKERS: usize = 1000;
+
+/// Request to create several markers at once.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchCreateRequest {
+    pub markers: Vec<CreateMarkerRequest>,
+}
+
+impl BatchCreateRequest {
+    /// Check the batch size; each marker is validated on its own.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.markers.len() > MAX_BATCH_MARKERS {
+            return Err(ValidationError::TooManyMarkers(self.markers.len()));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome for one marker in a batch create, in request order.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchItemResult {
+    Created {
+        marker: Marker,
+    },
+    Exists {
+        marker: Marker,
+    },
+    /// The marker failed validation and was not stored.
+    Error {
+        uuid: String,
+        #[serde(flatten)]
+        error: ApiError,
+    },
+}
+
+/// Response for batch marker creation.
+#[derive(Debug, Serialize)]
+pub struct BatchCreateResponse {
+    pub created: usize,
+    pub exists: usize,
+    pub errors: usize,
+    pub results: Vec<BatchItemResult>,
+}
+
+/// Most uuids accepted in one lookup request.
+pub const MAX_LOOKUP_UUIDS: usize = 1000;
+
+/// Request to look up markers by uuid.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LookupRequest {
+    pub uuids: Vec<String>,
+}
+
+impl LookupRequest {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.uuids.len() > MAX_LOOKUP_UUIDS {
+            return Err(ValidationError::TooManyUuids(self.uuids.len()));
+        }
+        for uuid in &self.uuids {
+            if uuid::Uuid::parse_str(uuid).is_err() {
+                return Err(ValidationError::InvalidUuid(uuid.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result for one uuid in a lookup, in request order.
+#[derive(Debug, Serialize)]
+pub struct LookupResult {
+    pub uuid: String,
+    pub status: &'static str, // "found" or "not_found"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marker: Option<Marker>,
+}
+
+/// Response for marker lookup.
+#[derive(Debug, Serialize)]
+pub struct LookupResponse {
+    pub results: Vec<LookupResult>,
+}
+
+/// Response for getting markers in the current visibility window.
 #[derive(Debug, Serialize)]
 pub struct GetMarkersResponse {
+    /// Whole hours, rounded down; `window_ms` is exact.
     pub window_hours: u32,
+    pub window_ms: i64,
     pub server_time_ms: i64,
     pub max_id: i64,
+    pub limit: i64,
+    pub has_more: bool,
+    /// Pass as `cursor` to fetch the next page; null on the last page.
+    pub next_cursor: Option<String>,
     pub markers: Vec<Marker>,
 }
 
+/// Query parameters for markers endpoint.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkersQuery {
+    pub bbox: Option<String>,
+    pub window_ms: Option<i64>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>, // defaults to and is capped at the server's page cap
+    pub format: Option<String>, // json (default), geojson, msgpack or cbor
+}
+
+impl MarkersQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(limit) = self.limit {
+            if limit < 1 {
+                return Err(ValidationError::InvalidPageLimit(limit));
+            }
+        }
+        self.cursor()?;
+        Ok(())
+    }
+
+    pub fn cursor(&self) -> Result<Option<MarkerCursor>, ValidationError> {
+        self.cursor.as_deref().map(MarkerCursor::parse).transpose()
+    }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+}
+
 /// Response for getting markers at a specific time.
 #[derive(Debug, Serialize)]
 pub struct GetMarkersAtResponse {
     pub at_epoch_ms: i64,
     pub window_hours: u32,
+    pub window_ms: i64,
+    pub markers: Vec<Marker>,
+}
+
+/// Position in a `(ts_epoch_ms, id)` ordered marker listing, serialized as `ts:id`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarkerCursor {
+    pub ts_epoch_ms: i64,
+    pub id: i64,
+}
+
+impl MarkerCursor {
+    /// Cursor pointing just past the given marker.
+    pub fn after(marker: &Marker) -> Self {
+        Self {
+            ts_epoch_ms: marker.ts_epoch_ms,
+            id: marker.id,
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, ValidationError> {
+        let invalid = || ValidationError::InvalidCursor(s.to_string());
+        let (ts, id) = s.split_once(':').ok_or_else(invalid)?;
+        let ts_epoch_ms = ts.parse::<i64>().map_err(|_| invalid())?;
+        let id = id.parse::<i64>().map_err(|_| invalid())?;
+        if ts_epoch_ms < 0 || id < 0 {
+            return Err(invalid());
+        }
+        Ok(Self { ts_epoch_ms, id })
+    }
+}
+
+impl std::fmt::Display for MarkerCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.ts_epoch_ms, self.id)
+    }
+}
+
+/// Query parameters for markers_range endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkersRangeQuery {
+    pub from: i64, // epoch milliseconds, inclusive
+    pub to: i64,   // epoch milliseconds, inclusive
+    pub cursor: Option<String>,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    pub bbox: Option<String>,
+}
+
+impl MarkersRangeQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_time_range(Some(self.from), Some(self.to))?;
+        if self.limit < 1 || self.limit > 1000 {
+            return Err(ValidationError::InvalidLimit(self.limit));
+        }
+        self.cursor()?;
+        Ok(())
+    }
+
+    pub fn cursor(&self) -> Result<Option<MarkerCursor>, ValidationError> {
+        self.cursor.as_deref().map(MarkerCursor::parse).transpose()
+    }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+}
+
+/// Response for markers_range endpoint.
+#[derive(Debug, Serialize)]
+pub struct GetMarkersRangeResponse {
+    pub from: i64,
+    pub to: i64,
+    pub limit: i64,
+    pub has_more: bool,
+    /// Pass as `cursor` to fetch the next page; null on the last page.
+    pub next_cursor: Option<String>,
     pub markers: Vec<Marker>,
 }
 
@@ -154,6 +561,9 @@ pub struct GetMarkersAtResponse {
 #[serde(deny_unknown_fields)]
 pub struct MarkersAtQuery {
     pub at: i64, // epoch milliseconds
+    pub bbox: Option<String>,
+    pub window_ms: Option<i64>,
+    pub format: Option<String>, // json (default), geojson, msgpack or cbor
 }
 
 impl MarkersAtQuery {
@@ -172,6 +582,10 @@ impl MarkersAtQuery {
         }
         Ok(())
     }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
 }
 
 /// Query parameters for log endpoint.
@@ -180,14 +594,24 @@ impl MarkersAtQuery {
 pub struct LogQuery {
     #[serde(default)]
     pub after_id: i64,
+    /// Page newest-first with ids below this; 0 starts from the tail.
+    pub before_id: Option<i64>,
     #[serde(default = "default_limit")]
     pub limit: i64,
+    /// Long-poll: wait up to this many ms for new entries if none are available.
+    #[serde(default)]
+    pub wait_ms: i64,
+    pub bbox: Option<String>,
+    pub format: Option<String>, // json (default), geojson, msgpack or cbor
 }
 
 fn default_limit() -> i64 {
     100
 }
 
+/// Maximum long-poll wait for the log endpoint (30 seconds).
+pub const MAX_WAIT_MS: i64 = 30_000;
+
 impl LogQuery {
     pub fn validate(&self) -> Result<(), ValidationError> {
         if self.after_id < 0 {
@@ -196,21 +620,473 @@ impl LogQuery {
         if self.limit < 1 || self.limit > 1000 {
             return Err(ValidationError::InvalidLimit(self.limit));
         }
+        if !(0..=MAX_WAIT_MS).contains(&self.wait_ms) {
+            return Err(ValidationError::InvalidWaitMs(self.wait_ms));
+        }
+        if let Some(before_id) = self.before_id {
+            if before_id < 0 || self.after_id != 0 || self.wait_ms != 0 {
+                return Err(ValidationError::InvalidBeforeId(before_id));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+}
+
+/// Query parameters for the log stream and dump endpoints.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogStreamQuery {
+    #[serde(default)]
+    pub after_id: i64,
+}
+
+impl LogStreamQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.after_id < 0 {
+            return Err(ValidationError::InvalidLimit(self.after_id));
+        }
+        Ok(())
+    }
+}
+
+/// Query parameters for nearby markers endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NearQuery {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_km: f64,
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    pub window_ms: Option<i64>,
+}
+
+/// Maximum search radius (roughly half the Earth's circumference).
+pub const MAX_RADIUS_KM: f64 = 20_000.0;
+
+impl NearQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(ValidationError::InvalidLatitude(self.lat));
+        }
+        if !(-180.0..=180.0).contains(&self.lon) {
+            return Err(ValidationError::InvalidLongitude(self.lon));
+        }
+        if !(self.radius_km > 0.0 && self.radius_km <= MAX_RADIUS_KM) {
+            return Err(ValidationError::InvalidRadius(self.radius_km));
+        }
+        if self.limit < 1 || self.limit > 1000 {
+            return Err(ValidationError::InvalidLimit(self.limit));
+        }
+        Ok(())
+    }
+}
+
+/// A marker with its distance and bearing from a search point.
+#[derive(Debug, Serialize)]
+pub struct NearbyMarker {
+    #[serde(flatten)]
+    pub marker: Marker,
+    pub distance_km: f64,
+    pub bearing_deg: f64,
+}
+
+/// Response for nearby markers endpoint.
+#[derive(Debug, Serialize)]
+pub struct GetNearbyResponse {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_km: f64,
+    pub limit: i64,
+    pub window_hours: u32,
+    pub window_ms: i64,
+    pub server_time_ms: i64,
+    pub markers: Vec<NearbyMarker>,
+}
+
+/// Query parameters for label search endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchQuery {
+    pub q: String,
+    pub from: Option<i64>, // epoch milliseconds, inclusive
+    pub to: Option<i64>,   // epoch milliseconds, inclusive
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// Deepest page reachable with offset paging in search results.
+pub const MAX_SEARCH_OFFSET: i64 = 10_000;
+
+impl SearchQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let q = self.q.trim();
+        if !q.chars().any(char::is_alphanumeric) || q.chars().count() > 256 {
+            return Err(ValidationError::InvalidQuery(self.q.clone()));
+        }
+        validate_time_range(self.from, self.to)?;
+        if self.limit < 1 || self.limit > 1000 {
+            return Err(ValidationError::InvalidLimit(self.limit));
+        }
+        if !(0..=MAX_SEARCH_OFFSET).contains(&self.offset) {
+            return Err(ValidationError::InvalidOffset(self.offset));
+        }
+        Ok(())
+    }
+}
+
+/// Most buckets a histogram request may produce.
+pub const MAX_HISTOGRAM_BUCKETS: i64 = 10_000;
+
+/// Query parameters for histogram endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HistogramQuery {
+    pub from: i64, // epoch milliseconds, inclusive
+    pub to: i64,   // epoch milliseconds, inclusive
+    pub bucket_ms: i64,
+    pub icon_id: Option<String>,
+}
+
+impl HistogramQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_time_range(Some(self.from), Some(self.to))?;
+        if self.bucket_ms < 1 || self.bucket_count() > MAX_HISTOGRAM_BUCKETS {
+            return Err(ValidationError::InvalidBucketMs(self.bucket_ms));
+        }
+        if let Some(icon_id) = &self.icon_id {
+            if icon_id.is_empty() {
+                return Err(ValidationError::EmptyIconId);
+            }
+            if icon_id.len() > 64 {
+                return Err(ValidationError::IconIdTooLong(icon_id.len()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of buckets covering `from..=to`, saturating at `i64::MAX`.
+    /// Only meaningful once validated.
+    pub fn bucket_count(&self) -> i64 {
+        // Wide enough for any pair of i64 bounds
+        let span = self.to as i128 - self.from as i128;
+        let count = span / self.bucket_ms.max(1) as i128 + 1;
+        i64::try_from(count).unwrap_or(i64::MAX)
+    }
+}
+
+/// Marker count for one histogram bucket, covering `start_ms..start_ms + bucket_ms`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub start_ms: i64,
+    pub count: i64,
+}
+
+/// Response for histogram endpoint.
+#[derive(Debug, Serialize)]
+pub struct HistogramResponse {
+    pub from: i64,
+    pub to: i64,
+    pub bucket_ms: i64,
+    pub icon_id: Option<String>,
+    pub total: i64,
+    pub buckets: Vec<HistogramBucket>,
+}
+
+/// Query parameters for file export endpoints.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportQuery {
+    pub from: i64, // epoch milliseconds, inclusive
+    pub to: i64,   // epoch milliseconds, inclusive
+    pub icon_id: Option<String>,
+    pub bbox: Option<String>,
+}
+
+impl ExportQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate_time_range(Some(self.from), Some(self.to))?;
+        if let Some(icon_id) = &self.icon_id {
+            if icon_id.is_empty() {
+                return Err(ValidationError::EmptyIconId);
+            }
+            if icon_id.len() > 64 {
+                return Err(ValidationError::IconIdTooLong(icon_id.len()));
+            }
+        }
         Ok(())
     }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+}
+
+/// Check optional `from`/`to` bounds: non-negative and in order.
+fn validate_time_range(from: Option<i64>, to: Option<i64>) -> Result<(), ValidationError> {
+    let (from, to) = (from.unwrap_or(0), to.unwrap_or(i64::MAX));
+    if from < 0 || to < 0 || from > to {
+        return Err(ValidationError::InvalidTimeRange(from, to));
+    }
+    Ok(())
+}
+
+/// A label search result.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub marker: Marker,
+    /// HTML-escaped label with matches wrapped in `<mark>` tags.
+    pub highlight: String,
+    /// Relevance (higher is better).
+    pub score: f64,
+}
+
+/// Response for label search endpoint.
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub q: String,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+    pub has_more: bool,
+    pub results: Vec<SearchHit>,
+}
+
+/// Highest zoom level accepted by the clusters endpoint.
+pub const MAX_CLUSTER_ZOOM: i64 = 20;
+
+/// Zoom level from which the clusters endpoint returns individual markers.
+pub const EXPAND_CLUSTER_ZOOM: i64 = 12;
+
+/// Query parameters for clusters endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClustersQuery {
+    pub zoom: i64,
+    pub bbox: Option<String>,
+    pub window_ms: Option<i64>,
+}
+
+impl ClustersQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if !(0..=MAX_CLUSTER_ZOOM).contains(&self.zoom) {
+            return Err(ValidationError::InvalidZoom(self.zoom));
+        }
+        if self.expanded() && self.bbox.is_none() {
+            return Err(ValidationError::BboxRequired(self.zoom));
+        }
+        Ok(())
+    }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+
+    /// Grid cell size in degrees: 45° at zoom 0, halving with each level.
+    pub fn cell_deg(&self) -> f64 {
+        45.0 / 2f64.powi(self.zoom as i32)
+    }
+
+    /// Whether this zoom is high enough to return markers instead of clusters.
+    pub fn expanded(&self) -> bool {
+        self.zoom >= EXPAND_CLUSTER_ZOOM
+    }
+}
+
+/// Markers grouped into one grid cell.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cluster {
+    /// Centroid of the cell's markers.
+    pub lat: f64,
+    pub lon: f64,
+    pub count: i64,
+    /// Marker count per icon_id.
+    pub icons: BTreeMap<String, i64>,
+}
+
+/// Response for clusters endpoint. Below `EXPAND_CLUSTER_ZOOM` only `clusters`
+/// is filled (the largest ones, up to the page cap), from there on only
+/// `markers` (the newest ones in the bbox, up to the page cap). `has_more` is
+/// set when some were left out.
+#[derive(Debug, Serialize)]
+pub struct GetClustersResponse {
+    pub zoom: i64,
+    pub cell_deg: f64,
+    pub window_hours: u32,
+    pub window_ms: i64,
+    pub server_time_ms: i64,
+    pub clusters: Vec<Cluster>,
+    pub markers: Vec<Marker>,
+    pub has_more: bool,
+}
+
+/// Deepest vector tile zoom level.
+pub const MAX_TILE_ZOOM: u32 = 22;
+
+/// Web Mercator tile address, from a `/tiles/{z}/{x}/{y}.mvt` path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileCoord {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileCoord {
+    /// Parse and validate path segments; `y` carries the `.mvt` extension.
+    pub fn parse(z: &str, x: &str, y: &str) -> Result<Self, ValidationError> {
+        let invalid = || ValidationError::InvalidTile(format!("{}/{}/{}", z, x, y));
+        let z: u32 = z.parse().map_err(|_| invalid())?;
+        let x: u32 = x.parse().map_err(|_| invalid())?;
+        let y: u32 = y
+            .strip_suffix(".mvt")
+            .and_then(|y| y.parse().ok())
+            .ok_or_else(invalid)?;
+        if z > MAX_TILE_ZOOM || x >= 1 << z || y >= 1 << z {
+            return Err(invalid());
+        }
+        Ok(Self { z, x, y })
+    }
+
+    pub fn bbox(&self) -> BBox {
+        crate::geo::tile_bbox(self.z, self.x, self.y)
+    }
+}
+
+/// Query parameters for vector tile endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileQuery {
+    pub window_ms: Option<i64>,
+}
+
+/// Finest heatmap grid, in degrees.
+pub const MIN_HEATMAP_CELL_DEG: f64 = 0.01;
+
+/// Coarsest heatmap grid, in degrees.
+pub const MAX_HEATMAP_CELL_DEG: f64 = 45.0;
+
+/// Most grid cells a heatmap request may cover.
+pub const MAX_HEATMAP_CELLS: f64 = 1_000_000.0;
+
+fn default_cell_deg() -> f64 {
+    1.0
+}
+
+/// Query parameters for heatmap endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HeatmapQuery {
+    #[serde(default = "default_cell_deg")]
+    pub cell_deg: f64,
+    pub at: Option<i64>, // epoch milliseconds, end of the window (default now)
+    pub window_ms: Option<i64>,
+    pub half_life_ms: Option<i64>,
+    pub bbox: Option<String>,
+}
+
+impl HeatmapQuery {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(at) = self.at {
+            if at <= 0 {
+                return Err(ValidationError::InvalidTimestamp(at.to_string()));
+            }
+        }
+        if !(MIN_HEATMAP_CELL_DEG..=MAX_HEATMAP_CELL_DEG).contains(&self.cell_deg) {
+            return Err(ValidationError::InvalidCellDeg(self.cell_deg));
+        }
+        if let Some(half_life_ms) = self.half_life_ms {
+            if half_life_ms < 1 {
+                return Err(ValidationError::InvalidHalfLife(half_life_ms));
+            }
+        }
+        // Bound the grid size over the requested area
+        let (lon_span, lat_span) = match self.bbox()? {
+            Some(bbox) if bbox.crosses_antimeridian() => (
+                bbox.max_lon + 360.0 - bbox.min_lon,
+                bbox.max_lat - bbox.min_lat,
+            ),
+            Some(bbox) => (bbox.max_lon - bbox.min_lon, bbox.max_lat - bbox.min_lat),
+            None => (360.0, 180.0),
+        };
+        let cells =
+            (lon_span / self.cell_deg).ceil().max(1.0) * (lat_span / self.cell_deg).ceil().max(1.0);
+        if cells > MAX_HEATMAP_CELLS {
+            return Err(ValidationError::InvalidCellDeg(self.cell_deg));
+        }
+        Ok(())
+    }
+
+    pub fn bbox(&self) -> Result<Option<BBox>, ValidationError> {
+        parse_bbox(&self.bbox)
+    }
+}
+
+/// One non-empty heatmap grid cell.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeatmapCell {
+    /// Centre of the cell.
+    pub lat: f64,
+    pub lon: f64,
+    pub count: i64,
+    /// Sum of marker weights; equals `count` without decay.
+    pub weight: f64,
+}
+
+/// Response for heatmap endpoint.
+#[derive(Debug, Serialize)]
+pub struct HeatmapResponse {
+    pub at_epoch_ms: i64,
+    pub window_hours: u32,
+    pub window_ms: i64,
+    pub cell_deg: f64,
+    pub half_life_ms: Option<i64>,
+    /// Largest cell weight, for normalising colours.
+    pub max_weight: f64,
+    pub cells: Vec<HeatmapCell>,
 }
 
 /// Response for log endpoint.
 #[derive(Debug, Serialize)]
 pub struct GetLogResponse {
     pub after_id: i64,
+    /// Set when paging newest-first; entries are then in descending id order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_id: Option<i64>,
     pub limit: i64,
     pub server_time_ms: i64,
     pub max_id: i64,
+    /// Lowest id in `entries`; pass as `before_id` to load older entries.
+    pub min_id: i64,
     pub has_more: bool,
     pub entries: Vec<Marker>,
 }
 
+/// Message sent to WebSocket clients on `/ws`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsServerMessage {
+    /// Acknowledges a create request ("created" or "exists").
+    Ack(CreateMarkerResponse),
+    /// A create request was rejected.
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uuid: Option<String>,
+        #[serde(flatten)]
+        error: ApiError,
+    },
+    /// A newly appended log entry.
+    Entry(Marker),
+}
+
 /// Icon metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Icon {
@@ -257,8 +1133,32 @@ impl ApiError {
             | ValidationError::IconIdTooLong(_)
             | ValidationError::IconIdNotFound(_) => Self::with_field(e.to_string(), "icon_id"),
             ValidationError::LabelTooLong(_) => Self::with_field(e.to_string(), "label"),
-            ValidationError::InvalidLimit(_) => Self::with_field(e.to_string(), "limit"),
+            ValidationError::InvalidLimit(_) | ValidationError::InvalidPageLimit(_) => {
+                Self::with_field(e.to_string(), "limit")
+            }
             ValidationError::InvalidTimestamp(_) => Self::with_field(e.to_string(), "at"),
+            ValidationError::InvalidLastEventId(_) => {
+                Self::with_field(e.to_string(), "Last-Event-ID")
+            }
+            ValidationError::InvalidWaitMs(_) => Self::with_field(e.to_string(), "wait_ms"),
+            ValidationError::InvalidBbox(_) | ValidationError::BboxRequired(_) => {
+                Self::with_field(e.to_string(), "bbox")
+            }
+            ValidationError::InvalidRadius(_) => Self::with_field(e.to_string(), "radius_km"),
+            ValidationError::InvalidQuery(_) => Self::with_field(e.to_string(), "q"),
+            ValidationError::InvalidTimeRange(_, _) => Self::with_field(e.to_string(), "from"),
+            ValidationError::InvalidOffset(_) => Self::with_field(e.to_string(), "offset"),
+            ValidationError::InvalidCursor(_) => Self::with_field(e.to_string(), "cursor"),
+            ValidationError::InvalidWindow(_, _) => Self::with_field(e.to_string(), "window_ms"),
+            ValidationError::InvalidZoom(_) => Self::with_field(e.to_string(), "zoom"),
+            ValidationError::InvalidBucketMs(_) => Self::with_field(e.to_string(), "bucket_ms"),
+            ValidationError::InvalidCellDeg(_) => Self::with_field(e.to_string(), "cell_deg"),
+            ValidationError::InvalidHalfLife(_) => Self::with_field(e.to_string(), "half_life_ms"),
+            ValidationError::TooManyUuids(_) => Self::with_field(e.to_string(), "uuids"),
+            ValidationError::TooManyMarkers(_) => Self::with_field(e.to_string(), "markers"),
+            ValidationError::InvalidBeforeId(_) => Self::with_field(e.to_string(), "before_id"),
+            ValidationError::InvalidFormat(_) => Self::with_field(e.to_string(), "format"),
+            ValidationError::InvalidTile(_) => Self::with_field(e.to_string(), "tile"),
         }
     }
 }
@@ -485,6 +1385,7 @@ mod tests {
         let query: LogQuery = serde_json::from_str(json).unwrap();
         assert_eq!(query.after_id, 0);
         assert_eq!(query.limit, 100);
+        assert_eq!(query.wait_ms, 0);
     }
 
     #[test]
@@ -499,23 +1400,370 @@ mod tests {
     fn test_log_query_validation() {
         let valid = LogQuery {
             after_id: 0,
+            before_id: None,
             limit: 100,
+            wait_ms: 0,
+            bbox: None,
+            format: None,
         };
         assert!(valid.validate().is_ok());
 
         let invalid_limit_low = LogQuery {
             after_id: 0,
+            before_id: None,
             limit: 0,
+            wait_ms: 0,
+            bbox: None,
+            format: None,
         };
         assert!(invalid_limit_low.validate().is_err());
 
         let invalid_limit_high = LogQuery {
             after_id: 0,
+            before_id: None,
             limit: 1001,
+            wait_ms: 0,
+            bbox: None,
+            format: None,
         };
         assert!(invalid_limit_high.validate().is_err());
     }
 
+    #[test]
+    fn test_log_query_wait_ms_validation() {
+        let long_poll = LogQuery {
+            after_id: 0,
+            before_id: None,
+            limit: 100,
+            wait_ms: MAX_WAIT_MS,
+            bbox: None,
+            format: None,
+        };
+        assert!(long_poll.validate().is_ok());
+
+        let negative = LogQuery {
+            wait_ms: -1,
+            ..long_poll
+        };
+        assert_eq!(negative.validate(), Err(ValidationError::InvalidWaitMs(-1)));
+
+        let too_long = LogQuery {
+            wait_ms: MAX_WAIT_MS + 1,
+            ..negative
+        };
+        assert_eq!(
+            too_long.validate(),
+            Err(ValidationError::InvalidWaitMs(MAX_WAIT_MS + 1))
+        );
+    }
+
+    #[test]
+    fn test_log_query_before_id_validation() {
+        let tail = LogQuery {
+            after_id: 0,
+            before_id: Some(0),
+            limit: 100,
+            wait_ms: 0,
+            bbox: None,
+            format: None,
+        };
+        assert!(tail.validate().is_ok());
+
+        let with_after_id = LogQuery {
+            after_id: 5,
+            before_id: Some(10),
+            ..tail
+        };
+        assert_eq!(
+            with_after_id.validate(),
+            Err(ValidationError::InvalidBeforeId(10))
+        );
+
+        let with_wait = LogQuery {
+            after_id: 0,
+            wait_ms: 1000,
+            ..with_after_id
+        };
+        assert_eq!(
+            with_wait.validate(),
+            Err(ValidationError::InvalidBeforeId(10))
+        );
+    }
+
+    #[test]
+    fn test_bbox_parse() {
+        let bbox = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+        assert_eq!(bbox.min_lon, 4.5);
+        assert_eq!(bbox.min_lat, 57.9);
+        assert_eq!(bbox.max_lon, 31.2);
+        assert_eq!(bbox.max_lat, 71.2);
+        assert!(!bbox.crosses_antimeridian());
+
+        // Whitespace around values is tolerated
+        assert!(BBox::parse("-10, -10, 10, 10").is_ok());
+    }
+
+    #[test]
+    fn test_bbox_parse_invalid() {
+        for s in [
+            "",
+            "1,2,3",
+            "1,2,3,4,5",
+            "a,b,c,d",
+            "-181,0,10,10",
+            "0,-91,10,10",
+            "0,10,10,-10", // min_lat > max_lat
+        ] {
+            assert_eq!(
+                BBox::parse(s),
+                Err(ValidationError::InvalidBbox(s.to_string())),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_bbox_contains() {
+        let bbox = BBox::parse("4.5,57.9,31.2,71.2").unwrap();
+        assert!(bbox.contains(59.91, 10.75));
+        assert!(bbox.contains(57.9, 4.5)); // edges inclusive
+        assert!(!bbox.contains(55.0, 10.75));
+        assert!(!bbox.contains(59.91, -3.0));
+    }
+
+    #[test]
+    fn test_bbox_antimeridian() {
+        // Box around Fiji, from 170E across to 170W
+        let bbox = BBox::parse("170,-25,-170,-10").unwrap();
+        assert!(bbox.crosses_antimeridian());
+        assert!(bbox.contains(-18.0, 178.0));
+        assert!(bbox.contains(-18.0, -179.0));
+        assert!(bbox.contains(-18.0, 180.0));
+        assert!(!bbox.contains(-18.0, 0.0));
+        assert!(!bbox.contains(-18.0, 160.0));
+    }
+
+    #[test]
+    fn test_near_query_validation() {
+        let valid = NearQuery {
+            lat: 59.91,
+            lon: 10.75,
+            radius_km: 20.0,
+            limit: 100,
+            window_ms: None,
+        };
+        assert!(valid.validate().is_ok());
+
+        let bad_radius = NearQuery {
+            radius_km: 0.0,
+            ..valid
+        };
+        assert_eq!(
+            bad_radius.validate(),
+            Err(ValidationError::InvalidRadius(0.0))
+        );
+
+        let bad_lat = NearQuery {
+            lat: 91.0,
+            radius_km: 20.0,
+            ..bad_radius
+        };
+        assert_eq!(
+            bad_lat.validate(),
+            Err(ValidationError::InvalidLatitude(91.0))
+        );
+    }
+
+    #[test]
+    fn test_histogram_query_validation() {
+        let valid = HistogramQuery {
+            from: 1705579200000,
+            to: 1705665600000,
+            bucket_ms: 3_600_000,
+            icon_id: None,
+        };
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.bucket_count(), 25);
+
+        let too_many = HistogramQuery {
+            bucket_ms: 1,
+            ..valid
+        };
+        assert_eq!(
+            too_many.validate(),
+            Err(ValidationError::InvalidBucketMs(1))
+        );
+
+        let empty_icon = HistogramQuery {
+            bucket_ms: 3_600_000,
+            icon_id: Some(String::new()),
+            ..too_many
+        };
+        assert_eq!(empty_icon.validate(), Err(ValidationError::EmptyIconId));
+
+        // The widest range with the narrowest buckets doesn't overflow
+        let extreme = HistogramQuery {
+            from: 0,
+            to: i64::MAX,
+            bucket_ms: 1,
+            icon_id: None,
+        };
+        assert_eq!(extreme.bucket_count(), i64::MAX);
+        assert_eq!(extreme.validate(), Err(ValidationError::InvalidBucketMs(1)));
+        let extreme = HistogramQuery {
+            bucket_ms: i64::MAX,
+            ..extreme
+        };
+        assert_eq!(extreme.bucket_count(), 2);
+        assert!(extreme.validate().is_ok());
+    }
+
+    #[test]
+    fn test_export_query_validation() {
+        let valid = ExportQuery {
+            from: 1705579200000,
+            to: 1705665600000,
+            icon_id: Some("ship".to_string()),
+            bbox: Some("4.5,57.9,31.2,71.2".to_string()),
+        };
+        assert!(valid.validate().is_ok());
+        assert!(valid.bbox().unwrap().is_some());
+
+        let reversed = ExportQuery {
+            from: 1705665600000,
+            to: 1705579200000,
+            ..valid
+        };
+        assert_eq!(
+            reversed.validate(),
+            Err(ValidationError::InvalidTimeRange(
+                1705665600000,
+                1705579200000
+            ))
+        );
+    }
+
+    #[test]
+    fn test_tile_coord_parse() {
+        assert_eq!(
+            TileCoord::parse("10", "542", "297.mvt"),
+            Ok(TileCoord {
+                z: 10,
+                x: 542,
+                y: 297
+            })
+        );
+        assert_eq!(
+            TileCoord::parse("1", "0", "2.mvt"),
+            Err(ValidationError::InvalidTile("1/0/2.mvt".to_string()))
+        );
+        assert!(TileCoord::parse("10", "542", "297").is_err());
+        assert!(TileCoord::parse("23", "0", "0.mvt").is_err());
+    }
+
+    #[test]
+    fn test_heatmap_query_validation() {
+        let valid = HeatmapQuery {
+            cell_deg: 1.0,
+            at: None,
+            window_ms: None,
+            half_life_ms: Some(3_600_000),
+            bbox: None,
+        };
+        assert!(valid.validate().is_ok());
+
+        // 0.1° over the whole world is 6.5 million cells
+        let too_fine = HeatmapQuery {
+            cell_deg: 0.1,
+            ..valid
+        };
+        assert_eq!(
+            too_fine.validate(),
+            Err(ValidationError::InvalidCellDeg(0.1))
+        );
+
+        let norway = HeatmapQuery {
+            bbox: Some("4.5,57.9,31.2,71.2".to_string()),
+            ..too_fine
+        };
+        assert!(norway.validate().is_ok());
+
+        let bad_half_life = HeatmapQuery {
+            half_life_ms: Some(0),
+            ..norway
+        };
+        assert_eq!(
+            bad_half_life.validate(),
+            Err(ValidationError::InvalidHalfLife(0))
+        );
+    }
+
+    #[test]
+    fn test_search_query_validation() {
+        let valid = SearchQuery {
+            q: "oil slick".to_string(),
+            from: Some(1705665600000),
+            to: Some(1705752000000),
+            limit: 50,
+            offset: 0,
+        };
+        assert!(valid.validate().is_ok());
+
+        let punctuation_only = SearchQuery {
+            q: " \"* ".to_string(),
+            ..valid
+        };
+        assert!(matches!(
+            punctuation_only.validate(),
+            Err(ValidationError::InvalidQuery(_))
+        ));
+
+        let reversed = SearchQuery {
+            q: "oil".to_string(),
+            from: Some(2),
+            to: Some(1),
+            ..punctuation_only
+        };
+        assert_eq!(
+            reversed.validate(),
+            Err(ValidationError::InvalidTimeRange(2, 1))
+        );
+
+        let deep = SearchQuery {
+            from: None,
+            to: None,
+            offset: MAX_SEARCH_OFFSET + 1,
+            ..reversed
+        };
+        assert_eq!(
+            deep.validate(),
+            Err(ValidationError::InvalidOffset(MAX_SEARCH_OFFSET + 1))
+        );
+    }
+
+    #[test]
+    fn test_marker_cursor_roundtrip() {
+        let cursor = MarkerCursor {
+            ts_epoch_ms: 1705665600000,
+            id: 42,
+        };
+        assert_eq!(cursor.to_string(), "1705665600000:42");
+        assert_eq!(MarkerCursor::parse("1705665600000:42"), Ok(cursor));
+    }
+
+    #[test]
+    fn test_marker_cursor_invalid() {
+        for s in ["", "42", "a:b", "1705665600000:", ":42", "-1:42"] {
+            assert_eq!(
+                MarkerCursor::parse(s),
+                Err(ValidationError::InvalidCursor(s.to_string())),
+                "{}",
+                s
+            );
+        }
+    }
+
     #[test]
     fn test_icon_serialization() {
         let icon = Icon {
@@ -1,30 +1,115 @@
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 
-use crate::db;
+use futures_util::TryStreamExt;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::Duration;
+
 use crate::models::{
-    ApiError, GetIconsResponse, GetLogResponse, GetMarkersAtResponse, GetMarkersResponse, Icon,
-    LogQuery, MarkersAtQuery,
+    ApiError, BBox, ClustersQuery, GetClustersResponse, GetIconsResponse, GetLogResponse,
+    GetMarkersAtResponse, GetMarkersRangeResponse, GetMarkersResponse, GetNearbyResponse,
+    HeatmapQuery, HeatmapResponse, HistogramQuery, HistogramResponse, Icon, LogQuery,
+    LookupRequest, LookupResponse, LookupResult, Marker, MarkerCursor, MarkersAtQuery,
+    MarkersQuery, MarkersRangeQuery, NearQuery, NearbyMarker, SearchQuery, SearchResponse,
+    ValidationError,
 };
+use crate::routes::cache;
+use crate::routes::format::{Encoded, ResponseFormat};
 use crate::state::AppState;
+use crate::{db, geo};
+
+/// Markers may be committed this long after the timestamp they get; a
+/// `markers_at` window ending earlier than that before now is final.
+const SETTLE_MARGIN_MS: i64 = 60_000;
+
+/// Whole hours in a window, for the `window_hours` response field.
+fn window_hours(window_ms: i64) -> u32 {
+    (window_ms / (60 * 60 * 1000)) as u32
+}
+
+/// GET /api/markers?bbox=...&window_ms=... - Get markers from the visibility window
+/// (24 hours unless configured or overridden).
+///
+/// Sends an `ETag` for the window's version and answers a matching
+/// `If-None-Match` with 304 Not Modified.
+///
+/// Responds with a GeoJSON FeatureCollection for `format=geojson` or
+/// `Accept: application/geo+json`.
+pub async fn get_markers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<MarkersQuery>,
+) -> Response {
+    // Validate query parameters
+    let (window_ms, bbox, cursor, format) = match query
+        .validate()
+        .and_then(|_| state.window(query.window_ms))
+        .and_then(|w| {
+            Ok((
+                w,
+                query.bbox()?,
+                query.cursor()?,
+                ResponseFormat::negotiate(query.format.as_deref(), &headers)?,
+            ))
+        }) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    // Computed before the query, so a concurrent insert can only make the tag stale
+    let etag = match db::get_window_version(&state.pool, window_ms).await {
+        Ok((max_id, first_id)) => format.etag(&format!("{}-{}", max_id, first_id)),
+        Err(e) => {
+            tracing::error!("Failed to get markers version: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response();
+        }
+    };
+    if cache::etag_matches(&headers, &etag) {
+        return cache::not_modified(&etag);
+    }
 
-/// GET /api/markers - Get markers from the last 24 hours.
-pub async fn get_markers(State(state): State<AppState>) -> Response {
     let server_time_ms = db::get_server_time_ms();
+    let limit = query
+        .limit
+        .unwrap_or(state.max_markers_page)
+        .min(state.max_markers_page);
 
-    match db::get_markers_last_24h(&state.pool).await {
-        Ok((markers, max_id)) => {
+    match db::get_markers_in_window(&state.pool, window_ms, bbox.as_ref(), cursor, limit).await {
+        Ok((markers, max_id, has_more)) => {
+            let next_cursor = if has_more {
+                markers.last().map(|m| MarkerCursor::after(m).to_string())
+            } else {
+                None
+            };
             let response = GetMarkersResponse {
-                window_hours: 24,
+                window_hours: window_hours(window_ms),
+                window_ms,
                 server_time_ms,
                 max_id,
+                limit,
+                has_more,
+                next_cursor,
                 markers,
             };
-            Json(response).into_response()
+            cache::with_cache_headers(
+                format.render_markers(&response, "markers", &response.markers),
+                Some(&etag),
+                cache::REVALIDATE,
+            )
         }
         Err(e) => {
             tracing::error!("Failed to get markers: {}", e);
@@ -37,13 +122,14 @@ pub async fn get_markers(State(state): State<AppState>) -> Response {
     }
 }
 
-/// GET /api/markers_at?at=<epoch_ms> - Get markers visible at a specific time.
-pub async fn get_markers_at(
+/// GET /api/markers/{uuid} - Get a single marker by uuid, regardless of its age.
+pub async fn get_marker(
     State(state): State<AppState>,
-    Query(query): Query<MarkersAtQuery>,
+    headers: HeaderMap,
+    Path(uuid): Path<String>,
 ) -> Response {
-    // Validate query parameters
-    if let Err(e) = query.validate() {
+    if uuid::Uuid::parse_str(&uuid).is_err() {
+        let e = ValidationError::InvalidUuid(uuid);
         return (
             StatusCode::BAD_REQUEST,
             Json(ApiError::from_validation_error(&e)),
@@ -51,14 +137,121 @@ pub async fn get_markers_at(
             .into_response();
     }
 
-    match db::get_markers_at(&state.pool, query.at).await {
+    match db::get_marker_by_uuid(&state.pool, &uuid).await {
+        Ok(Some(marker)) => ResponseFormat::encoding(&headers).render(&marker),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new(format!("Marker not found: {}", uuid))),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to get marker: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// POST /api/markers/lookup - Resolve a list of uuids to markers, regardless of their
+/// age. Results follow the request order.
+pub async fn lookup_markers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Encoded(req): Encoded<LookupRequest>,
+) -> Response {
+    if let Err(e) = req.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    match db::get_markers_by_uuids(&state.pool, &req.uuids).await {
+        Ok(markers) => {
+            let by_uuid: HashMap<&str, &Marker> =
+                markers.iter().map(|m| (m.uuid.as_str(), m)).collect();
+            let results = req
+                .uuids
+                .iter()
+                .map(|uuid| {
+                    let marker = by_uuid.get(uuid.as_str()).map(|m| (*m).clone());
+                    LookupResult {
+                        uuid: uuid.clone(),
+                        status: if marker.is_some() {
+                            "found"
+                        } else {
+                            "not_found"
+                        },
+                        marker,
+                    }
+                })
+                .collect();
+            ResponseFormat::encoding(&headers).render(&LookupResponse { results })
+        }
+        Err(e) => {
+            tracing::error!("Failed to look up markers: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/markers_at?at=<epoch_ms>&bbox=...&window_ms=... - Get markers visible at a
+/// specific time.
+///
+/// A window given by `window_ms` that ended more than `SETTLE_MARGIN_MS` ago can
+/// no longer change and is sent with an immutable `Cache-Control`. The default
+/// window follows server configuration, so responses relying on it are not.
+///
+/// Responds with a GeoJSON FeatureCollection like `get_markers`.
+pub async fn get_markers_at(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<MarkersAtQuery>,
+) -> Response {
+    // Validate query parameters
+    let (window_ms, bbox, format) = match query
+        .validate()
+        .and_then(|_| state.window(query.window_ms))
+        .and_then(|w| {
+            Ok((
+                w,
+                query.bbox()?,
+                ResponseFormat::negotiate(query.format.as_deref(), &headers)?,
+            ))
+        }) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    match db::get_markers_at(&state.pool, query.at, window_ms, bbox.as_ref()).await {
         Ok(markers) => {
             let response = GetMarkersAtResponse {
                 at_epoch_ms: query.at,
-                window_hours: 24,
+                window_hours: window_hours(window_ms),
+                window_ms,
                 markers,
             };
-            Json(response).into_response()
+            let response = format.render_markers(&response, "markers", &response.markers);
+            let settled = query.at < db::get_server_time_ms() - SETTLE_MARGIN_MS;
+            if settled && query.window_ms.is_some() {
+                cache::with_cache_headers(response, None, cache::IMMUTABLE)
+            } else {
+                response
+            }
         }
         Err(e) => {
             tracing::error!("Failed to get markers at time: {}", e);
@@ -71,8 +264,198 @@ pub async fn get_markers_at(
     }
 }
 
-/// GET /api/log?after_id=...&limit=... - Get log entries for polling/sync.
-pub async fn get_log(State(state): State<AppState>, Query(query): Query<LogQuery>) -> Response {
+/// GET /api/markers_range?from=...&to=...&cursor=...&limit=...&bbox=... - Get markers
+/// in an arbitrary time range, paged with `next_cursor`.
+pub async fn get_markers_range(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<MarkersRangeQuery>,
+) -> Response {
+    // Validate query parameters
+    let (cursor, bbox) = match query
+        .validate()
+        .and_then(|_| Ok((query.cursor()?, query.bbox()?)))
+    {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    match db::get_markers_range(
+        &state.pool,
+        query.from,
+        query.to,
+        cursor,
+        query.limit,
+        bbox.as_ref(),
+    )
+    .await
+    {
+        Ok((markers, has_more)) => {
+            let next_cursor = if has_more {
+                markers.last().map(|m| MarkerCursor::after(m).to_string())
+            } else {
+                None
+            };
+            let response = GetMarkersRangeResponse {
+                from: query.from,
+                to: query.to,
+                limit: query.limit,
+                has_more,
+                next_cursor,
+                markers,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get markers in range: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/clusters?zoom=...&bbox=...&window_ms=... - Get markers from the visibility
+/// window grouped into grid cells, or the markers themselves at high zoom.
+pub async fn get_clusters(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<ClustersQuery>,
+) -> Response {
+    // Validate query parameters
+    let (window_ms, bbox) = match query
+        .validate()
+        .and_then(|_| state.window(query.window_ms))
+        .and_then(|w| Ok((w, query.bbox()?)))
+    {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let server_time_ms = db::get_server_time_ms();
+
+    let result = match (&bbox, query.expanded()) {
+        // validate() requires a bbox when expanded
+        (Some(bbox), true) => {
+            db::get_latest_markers_in_window(&state.pool, window_ms, bbox, state.max_markers_page)
+                .await
+                .map(|(markers, has_more)| (Vec::new(), markers, has_more))
+        }
+        _ => db::get_marker_clusters(
+            &state.pool,
+            window_ms,
+            query.cell_deg(),
+            bbox.as_ref(),
+            state.max_markers_page,
+        )
+        .await
+        .map(|(clusters, has_more)| (clusters, Vec::new(), has_more)),
+    };
+
+    match result {
+        Ok((clusters, markers, has_more)) => {
+            let response = GetClustersResponse {
+                zoom: query.zoom,
+                cell_deg: query.cell_deg(),
+                window_hours: window_hours(window_ms),
+                window_ms,
+                server_time_ms,
+                clusters,
+                markers,
+                has_more,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get clusters: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/heatmap?cell_deg=...&at=...&window_ms=...&half_life_ms=...&bbox=... - Get
+/// a marker density grid over the visibility window ending at `at`.
+pub async fn get_heatmap(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<HeatmapQuery>,
+) -> Response {
+    // Validate query parameters
+    let (window_ms, bbox) = match query
+        .validate()
+        .and_then(|_| state.window(query.window_ms))
+        .and_then(|w| Ok((w, query.bbox()?)))
+    {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let at = query.at.unwrap_or_else(db::get_server_time_ms);
+
+    match db::get_heatmap(
+        &state.pool,
+        at - window_ms,
+        at,
+        query.cell_deg,
+        query.half_life_ms,
+        bbox.as_ref(),
+    )
+    .await
+    {
+        Ok(cells) => {
+            let response = HeatmapResponse {
+                at_epoch_ms: at,
+                window_hours: window_hours(window_ms),
+                window_ms,
+                cell_deg: query.cell_deg,
+                half_life_ms: query.half_life_ms,
+                max_weight: cells.iter().map(|c| c.weight).fold(0.0, f64::max),
+                cells,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get heatmap: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/histogram?from=...&to=...&bucket_ms=...&icon_id=... - Count markers per
+/// time bucket, for a timeline scrubber.
+pub async fn get_histogram(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<HistogramQuery>,
+) -> Response {
     // Validate query parameters
     if let Err(e) = query.validate() {
         return (
@@ -82,19 +465,205 @@ pub async fn get_log(State(state): State<AppState>, Query(query): Query<LogQuery
             .into_response();
     }
 
+    match db::get_histogram(
+        &state.pool,
+        query.from,
+        query.to,
+        query.bucket_ms,
+        query.bucket_count(),
+        query.icon_id.as_deref(),
+    )
+    .await
+    {
+        Ok(buckets) => {
+            let response = HistogramResponse {
+                from: query.from,
+                to: query.to,
+                bucket_ms: query.bucket_ms,
+                total: buckets.iter().map(|b| b.count).sum(),
+                icon_id: query.icon_id,
+                buckets,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get histogram: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Orders nearby markers by distance, for a max-heap of the nearest ones.
+struct ByDistance(NearbyMarker);
+
+impl PartialEq for ByDistance {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for ByDistance {}
+
+impl PartialOrd for ByDistance {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByDistance {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.distance_km.total_cmp(&other.0.distance_km)
+    }
+}
+
+/// GET /api/markers/near?lat=...&lon=...&radius_km=...&limit=... - Get markers from
+/// the visibility window within a great-circle distance, nearest first.
+pub async fn get_markers_near(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<NearQuery>,
+) -> Response {
+    // Validate query parameters
+    let window_ms = match query.validate().and_then(|_| state.window(query.window_ms)) {
+        Ok(window_ms) => window_ms,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
     let server_time_ms = db::get_server_time_ms();
 
-    match db::get_log_after(&state.pool, query.after_id, query.limit).await {
-        Ok((entries, max_id, has_more)) => {
+    // Prefilter in SQL with the box around the search circle
+    let bbox = geo::bbox_around(query.lat, query.lon, query.radius_km);
+
+    // Every candidate in the box is considered, so the nearest are found
+    // however many markers the window holds; only the `limit` nearest so far
+    // are kept
+    let (lat, lon, radius_km) = (query.lat, query.lon, query.radius_km);
+    let limit = query.limit as usize;
+    let nearby = db::stream_markers_range(
+        state.pool.clone(),
+        server_time_ms - window_ms,
+        i64::MAX,
+        None,
+        Some(bbox),
+    )
+    .try_filter_map(|marker| async move {
+        let distance_km = geo::distance_km(lat, lon, marker.lat, marker.lon);
+        if distance_km > radius_km {
+            return Ok(None);
+        }
+        let bearing_deg = geo::bearing_deg(lat, lon, marker.lat, marker.lon);
+        Ok(Some(ByDistance(NearbyMarker {
+            marker,
+            distance_km,
+            bearing_deg,
+        })))
+    })
+    .try_fold(BinaryHeap::new(), |mut nearest, candidate| async move {
+        nearest.push(candidate);
+        if nearest.len() > limit {
+            nearest.pop(); // The farthest
+        }
+        Ok(nearest)
+    })
+    .await;
+
+    match nearby {
+        Ok(nearest) => {
+            let markers: Vec<NearbyMarker> = nearest
+                .into_sorted_vec()
+                .into_iter()
+                .map(|ByDistance(marker)| marker)
+                .collect();
+
+            let response = GetNearbyResponse {
+                lat: query.lat,
+                lon: query.lon,
+                radius_km: query.radius_km,
+                limit: query.limit,
+                window_hours: window_hours(window_ms),
+                window_ms,
+                server_time_ms,
+                markers,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get nearby markers: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// GET /api/log?after_id=...&limit=...&wait_ms=...&bbox=... - Get log entries for polling/sync.
+///
+/// With `before_id`, pages the log newest-first instead (`before_id=0` starts
+/// from the tail).
+///
+/// With `wait_ms`, an empty result is held back until a new entry is committed
+/// by this server or the wait elapses (long-polling).
+///
+/// Sends an `ETag` for the log head and answers a matching `If-None-Match` with
+/// 304 Not Modified, after the wait when long-polling.
+///
+/// Responds with a GeoJSON FeatureCollection like `get_markers`.
+pub async fn get_log(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LogQuery>,
+) -> Response {
+    // Validate query parameters
+    let (bbox, format) = match query.validate().and_then(|_| {
+        Ok((
+            query.bbox()?,
+            ResponseFormat::negotiate(query.format.as_deref(), &headers)?,
+        ))
+    }) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    match read_log(&state, &headers, &query, bbox.as_ref(), format).await {
+        Ok((head, None)) => cache::not_modified(&log_etag(format, head)),
+        Ok((head, Some((entries, max_id, has_more)))) => {
+            let min_id = match query.before_id {
+                Some(before_id) => entries.last().map(|m| m.id).unwrap_or(before_id),
+                None => entries.first().map(|m| m.id).unwrap_or(max_id),
+            };
             let response = GetLogResponse {
                 after_id: query.after_id,
+                before_id: query.before_id,
                 limit: query.limit,
-                server_time_ms,
+                server_time_ms: db::get_server_time_ms(),
                 max_id,
+                min_id,
                 has_more,
                 entries,
             };
-            Json(response).into_response()
+            cache::with_cache_headers(
+                format.render_markers(&response, "entries", &response.entries),
+                Some(&log_etag(format, head)),
+                cache::REVALIDATE,
+            )
         }
         Err(e) => {
             tracing::error!("Failed to get log: {}", e);
@@ -107,9 +676,115 @@ pub async fn get_log(State(state): State<AppState>, Query(query): Query<LogQuery
     }
 }
 
+/// Read a log page for `get_log`, long-polling if asked. Returns the log head
+/// read before the page, and no page if `If-None-Match` already has that head.
+async fn read_log(
+    state: &AppState,
+    headers: &HeaderMap,
+    query: &LogQuery,
+    bbox: Option<&BBox>,
+    format: ResponseFormat,
+) -> Result<(i64, Option<(Vec<Marker>, i64, bool)>), sqlx::Error> {
+    // Subscribe before querying so a commit during the query is not missed
+    let mut rx = state.watch_log();
+    rx.borrow_and_update();
+
+    // The head is read before the page, so a concurrent insert can only make
+    // the tag stale
+    let mut head = db::get_log_head(&state.pool).await?;
+    if let Some(before_id) = query.before_id {
+        if cache::etag_matches(headers, &log_etag(format, head)) {
+            return Ok((head, None));
+        }
+        let page = db::get_log_before(&state.pool, before_id, query.limit, bbox).await?;
+        return Ok((head, Some(page)));
+    }
+
+    let mut page = None;
+    if !cache::etag_matches(headers, &log_etag(format, head)) {
+        page = Some(db::get_log_after(&state.pool, query.after_id, query.limit, bbox).await?);
+    }
+
+    // Wait while there is nothing new for the client. Commits outside the
+    // bbox wake us too, so keep waiting until one matches or time runs out.
+    let deadline = tokio::time::Instant::now() + Duration::from_millis(query.wait_ms as u64);
+    let is_empty = |page: &Option<(Vec<Marker>, i64, bool)>| {
+        page.as_ref()
+            .map_or(true, |(entries, _, _)| entries.is_empty())
+    };
+    while query.wait_ms > 0 && is_empty(&page) {
+        match tokio::time::timeout_at(deadline, rx.changed()).await {
+            Ok(Ok(())) => {}
+            // Timed out, or the server is shutting down
+            _ => break,
+        }
+
+        head = db::get_log_head(&state.pool).await?;
+        page = None;
+        if !cache::etag_matches(headers, &log_etag(format, head)) {
+            page = Some(db::get_log_after(&state.pool, query.after_id, query.limit, bbox).await?);
+        }
+    }
+
+    Ok((head, page))
+}
+
+/// ETag for log responses: the log head they were read at.
+fn log_etag(format: ResponseFormat, head: i64) -> String {
+    format.etag(&head.to_string())
+}
+
+/// GET /api/search?q=...&from=...&to=...&limit=...&offset=... - Search marker labels.
+pub async fn search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SearchQuery>,
+) -> Response {
+    // Validate query parameters
+    if let Err(e) = query.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    match db::search_labels(
+        &state.pool,
+        &query.q,
+        query.from,
+        query.to,
+        query.limit,
+        query.offset,
+    )
+    .await
+    {
+        Ok((results, has_more)) => {
+            let response = SearchResponse {
+                q: query.q,
+                from: query.from,
+                to: query.to,
+                limit: query.limit,
+                offset: query.offset,
+                has_more,
+                results,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to search labels: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// GET /api/icons - Get available icons.
-pub async fn get_icons(State(state): State<AppState>) -> Json<GetIconsResponse> {
-    Json(GetIconsResponse {
+pub async fn get_icons(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    ResponseFormat::encoding(&headers).render(&GetIconsResponse {
         icons: (*state.icons).clone(),
     })
 }
@@ -0,0 +1,75 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+/// Cache-Control for responses that change as markers are added: caches may
+/// store them but must revalidate with the ETag before reuse.
+pub const REVALIDATE: &str = "public, no-cache";
+
+/// Cache-Control for responses that can never change.
+pub const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Whether `If-None-Match` lists `etag` (weak comparison) or is `*`.
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip(etag);
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || strip(tag) == etag)
+}
+
+/// 304 Not Modified carrying the current ETag. Tags differ per representation,
+/// so it varies on `Accept` like the full responses.
+pub fn not_modified(etag: &str) -> Response {
+    let mut response = with_cache_headers(
+        StatusCode::NOT_MODIFIED.into_response(),
+        Some(etag),
+        REVALIDATE,
+    );
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    response
+}
+
+/// Attach `ETag` (if any) and `Cache-Control` to a response.
+pub fn with_cache_headers(
+    mut response: Response,
+    etag: Option<&str>,
+    cache_control: &'static str,
+) -> Response {
+    let headers = response.headers_mut();
+    if let Some(value) = etag.and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = r#"W/"42-7""#;
+        assert!(etag_matches(&if_none_match(r#"W/"42-7""#), etag));
+        assert!(etag_matches(&if_none_match(r#""1-1", "42-7""#), etag));
+        assert!(etag_matches(&if_none_match("*"), etag));
+        assert!(!etag_matches(&if_none_match(r#"W/"43-7""#), etag));
+        assert!(!etag_matches(&HeaderMap::new(), etag));
+    }
+}
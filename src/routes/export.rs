@@ -0,0 +1,174 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    BoxError, Json,
+};
+use futures_util::{stream, StreamExt, TryStreamExt};
+
+use crate::db;
+use crate::export::{csv, gpx, kml};
+use crate::models::{ApiError, ExportQuery, LogStreamQuery};
+use crate::state::AppState;
+
+/// GET /api/export.csv?from=...&to=...&icon_id=...&bbox=... - Download markers
+/// in a time range as CSV.
+///
+/// Rows are streamed from the database as they are read, so exports of any
+/// size are sent without buffering.
+pub async fn export_csv(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    // Validate query parameters
+    let bbox = match query.validate().and_then(|_| query.bbox()) {
+        Ok(bbox) => bbox,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let records = db::stream_markers_range(state.pool, query.from, query.to, query.icon_id, bbox)
+        .map_ok(|marker| csv::record(&marker))
+        // Headers are already sent, so a failure can only cut the body short
+        .inspect_err(|e| tracing::error!("Failed to export markers: {}", e));
+    let body = stream::once(async { Ok(csv::HEADER.to_string()) }).chain(records);
+
+    (
+        [
+            (header::CONTENT_TYPE, csv::CONTENT_TYPE),
+            (
+                header::CONTENT_DISPOSITION,
+                r#"attachment; filename="markers.csv""#,
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// GET /api/export.kml?from=...&to=...&icon_id=...&bbox=... - Download markers
+/// in a time range as KML, for Google Earth and similar viewers.
+///
+/// Each icon becomes a shared style pointing at this server's icon URL, made
+/// absolute with the configured public URL. Request headers are not trusted
+/// for this, so without one the links stay relative.
+pub async fn export_kml(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    // Validate query parameters
+    let bbox = match query.validate().and_then(|_| query.bbox()) {
+        Ok(bbox) => bbox,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let start = kml::header(&state.icons, state.public_url.as_deref().unwrap_or(""));
+    let placemarks =
+        db::stream_markers_range(state.pool, query.from, query.to, query.icon_id, bbox)
+            .map_ok(|marker| kml::placemark(&marker))
+            // Headers are already sent, so a failure can only cut the body short
+            .inspect_err(|e| tracing::error!("Failed to export markers: {}", e));
+    let body = stream::once(async { Ok(start) })
+        .chain(placemarks)
+        .chain(stream::once(async { Ok(kml::FOOTER.to_string()) }));
+
+    (
+        [
+            (header::CONTENT_TYPE, kml::CONTENT_TYPE),
+            (
+                header::CONTENT_DISPOSITION,
+                r#"attachment; filename="markers.kml""#,
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// GET /api/export.gpx?from=...&to=...&icon_id=...&bbox=... - Download markers
+/// in a time range as GPX 1.1 waypoints, for handheld GPS units and chart
+/// plotters.
+pub async fn export_gpx(
+    State(state): State<AppState>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    // Validate query parameters
+    let bbox = match query.validate().and_then(|_| query.bbox()) {
+        Ok(bbox) => bbox,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let waypoints = db::stream_markers_range(state.pool, query.from, query.to, query.icon_id, bbox)
+        .map_ok(|marker| gpx::waypoint(&marker))
+        // Headers are already sent, so a failure can only cut the body short
+        .inspect_err(|e| tracing::error!("Failed to export markers: {}", e));
+    let body = stream::once(async { Ok(gpx::HEADER.to_string()) })
+        .chain(waypoints)
+        .chain(stream::once(async { Ok(gpx::FOOTER.to_string()) }));
+
+    (
+        [
+            (header::CONTENT_TYPE, gpx::CONTENT_TYPE),
+            (
+                header::CONTENT_DISPOSITION,
+                r#"attachment; filename="markers.gpx""#,
+            ),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
+
+/// GET /api/log.ndjson?after_id=... - Dump every log entry after `after_id` as
+/// newline-delimited JSON, one entry per line in id order.
+///
+/// Unlike `/api/log` there is no page limit: rows are streamed from a database
+/// cursor as the client reads them. Resume a cut-off dump from the last id
+/// received.
+pub async fn export_log_ndjson(
+    State(state): State<AppState>,
+    Query(query): Query<LogStreamQuery>,
+) -> Response {
+    // Validate query parameters
+    if let Err(e) = query.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    let lines = db::stream_log_after(state.pool, query.after_id)
+        .map_err(BoxError::from)
+        .and_then(|marker| async move {
+            let mut line = serde_json::to_vec(&marker)?;
+            line.push(b'\n');
+            Ok::<_, BoxError>(line)
+        })
+        // Headers are already sent, so a failure can only cut the body short
+        .inspect_err(|e| tracing::error!("Failed to dump log: {}", e));
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(lines),
+    )
+        .into_response()
+}
@@ -0,0 +1,351 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::export::geojson;
+use crate::models::{ApiError, Marker, ValidationError};
+
+/// Media type for MessagePack.
+pub const MSGPACK: &str = "application/msgpack";
+
+/// Media type for CBOR (RFC 8949).
+pub const CBOR: &str = "application/cbor";
+
+/// Representation of an API response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseFormat {
+    Json,
+    GeoJson,
+    MsgPack,
+    Cbor,
+}
+
+impl ResponseFormat {
+    /// Pick the format for a marker list from the `format` query parameter, or
+    /// else from `Accept`.
+    pub fn negotiate(format: Option<&str>, headers: &HeaderMap) -> Result<Self, ValidationError> {
+        match format {
+            Some("json") => Ok(Self::Json),
+            Some("geojson") => Ok(Self::GeoJson),
+            Some("msgpack") => Ok(Self::MsgPack),
+            Some("cbor") => Ok(Self::Cbor),
+            Some(other) => Err(ValidationError::InvalidFormat(other.to_string())),
+            None => Ok(preferred(headers, true)),
+        }
+    }
+
+    /// Pick the encoding for any other response from `Accept`. GeoJSON only
+    /// applies to marker lists, so it is not offered.
+    pub fn encoding(headers: &HeaderMap) -> Self {
+        preferred(headers, false)
+    }
+
+    /// Weak ETag for `version` in this representation.
+    pub fn etag(self, version: &str) -> String {
+        match self {
+            Self::Json => format!(r#"W/"{}""#, version),
+            Self::GeoJson => format!(r#"W/"{}-geojson""#, version),
+            Self::MsgPack => format!(r#"W/"{}-msgpack""#, version),
+            Self::Cbor => format!(r#"W/"{}-cbor""#, version),
+        }
+    }
+
+    /// Render a response. Binary encodings carry the same fields as JSON, with
+    /// MessagePack maps keyed by field name.
+    pub fn render<T: Serialize>(self, response: &T) -> Response {
+        let encoded = match self {
+            Self::Json | Self::GeoJson => Ok(Json(response).into_response()),
+            Self::MsgPack => rmp_serde::to_vec_named(response)
+                .map(|bytes| binary(MSGPACK, bytes))
+                .map_err(|e| e.to_string()),
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(response, &mut bytes)
+                    .map(|_| binary(CBOR, bytes))
+                    .map_err(|e| e.to_string())
+            }
+        };
+        let mut response = encoded.unwrap_or_else(|e| {
+            tracing::error!("Failed to encode response: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Encoding error: {}", e))),
+            )
+                .into_response()
+        });
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+
+    /// Render a response holding `markers` in its `items` field. GeoJSON turns
+    /// them into a FeatureCollection and keeps the other fields alongside.
+    pub fn render_markers<T: Serialize>(
+        self,
+        response: &T,
+        items: &str,
+        markers: &[Marker],
+    ) -> Response {
+        if self != Self::GeoJson {
+            return self.render(response);
+        }
+        let mut response = match serde_json::to_value(response) {
+            Ok(Value::Object(mut members)) => {
+                members.remove(items);
+                let collection = geojson::feature_collection(markers, members);
+                (
+                    [(header::CONTENT_TYPE, geojson::CONTENT_TYPE)],
+                    Json(collection),
+                )
+                    .into_response()
+            }
+            other => {
+                let e = match other {
+                    Ok(_) => "response is not an object".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                tracing::error!("Failed to encode GeoJSON response: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ApiError::new(format!("Encoding error: {}", e))),
+                )
+                    .into_response()
+            }
+        };
+        response
+            .headers_mut()
+            .insert(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+fn binary(content_type: &'static str, bytes: Vec<u8>) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], bytes).into_response()
+}
+
+/// The supported media range in `Accept` with the highest weight (`q`, 1 by
+/// default), the first listed on a tie, or JSON. Ranges with `q=0` or an
+/// invalid weight are skipped; `*/*` and `application/*` stand for JSON.
+fn preferred(headers: &HeaderMap, geojson: bool) -> ResponseFormat {
+    let mut best: Option<(f32, ResponseFormat)> = None;
+    let ranges = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for range in ranges {
+        let mut parts = range.split(';');
+        let media_type = parts.next().unwrap_or("").trim();
+        let format = match media_type {
+            "*/*" | "application/*" => ResponseFormat::Json,
+            _ => match media_type_format(media_type) {
+                Some(ResponseFormat::GeoJson) if !geojson => continue,
+                Some(format) => format,
+                None => continue,
+            },
+        };
+        let Some(q) = weight(parts) else { continue };
+        if q > 0.0 && best.map_or(true, |(best_q, _)| q > best_q) {
+            best = Some((q, format));
+        }
+    }
+    best.map_or(ResponseFormat::Json, |(_, format)| format)
+}
+
+/// The `q` parameter of a media range: 1 if absent, `None` if not a valid
+/// weight between 0 and 1.
+fn weight<'a>(params: impl Iterator<Item = &'a str>) -> Option<f32> {
+    for param in params {
+        let (name, value) = param.split_once('=').unwrap_or((param, ""));
+        if name.trim().eq_ignore_ascii_case("q") {
+            return value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|q| (0.0..=1.0).contains(q));
+        }
+    }
+    Some(1.0)
+}
+
+fn media_type_format(media_type: &str) -> Option<ResponseFormat> {
+    match media_type.trim().to_ascii_lowercase().as_str() {
+        "application/json" => Some(ResponseFormat::Json),
+        t if t == geojson::CONTENT_TYPE => Some(ResponseFormat::GeoJson),
+        "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+            Some(ResponseFormat::MsgPack)
+        }
+        t if t == CBOR => Some(ResponseFormat::Cbor),
+        _ => None,
+    }
+}
+
+/// Request body in JSON, MessagePack or CBOR, chosen by `Content-Type`.
+/// JSON bodies are read by axum's `Json`, with its rejections.
+pub struct Encoded<T>(pub T);
+
+impl<T, S> FromRequest<S> for Encoded<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let format = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .and_then(media_type_format);
+
+        let decoded = match format {
+            Some(ResponseFormat::MsgPack) => {
+                let bytes = Bytes::from_request(req, state)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+                rmp_serde::from_slice(&bytes).map_err(|e| e.to_string())
+            }
+            Some(ResponseFormat::Cbor) => {
+                let bytes = Bytes::from_request(req, state)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+                ciborium::from_reader(&bytes[..]).map_err(|e| e.to_string())
+            }
+            _ => {
+                let Json(value) = Json::<T>::from_request(req, state)
+                    .await
+                    .map_err(IntoResponse::into_response)?;
+                Ok(value)
+            }
+        };
+
+        decoded.map(Encoded).map_err(|e| {
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ApiError::new(format!("Invalid request body: {}", e))),
+            )
+                .into_response()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accept(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let none = HeaderMap::new();
+        assert_eq!(
+            ResponseFormat::negotiate(None, &none),
+            Ok(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(None, &accept("application/geo+json;q=0.9, */*;q=0.1")),
+            Ok(ResponseFormat::GeoJson)
+        );
+        // The query parameter wins over Accept
+        assert_eq!(
+            ResponseFormat::negotiate(Some("json"), &accept("application/geo+json")),
+            Ok(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(Some("xml"), &none),
+            Err(ValidationError::InvalidFormat("xml".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/msgpack")),
+            ResponseFormat::MsgPack
+        );
+        assert_eq!(
+            ResponseFormat::encoding(&accept("text/html, application/cbor")),
+            ResponseFormat::Cbor
+        );
+        // The first supported type listed wins a tie
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/json, application/msgpack")),
+            ResponseFormat::Json
+        );
+        // GeoJSON is only for marker lists
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/geo+json, application/cbor")),
+            ResponseFormat::Cbor
+        );
+        // q=0 rules a type out
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/msgpack;q=0, application/cbor")),
+            ResponseFormat::Cbor
+        );
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/cbor; q=0.0")),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/cbor;q=0.5")),
+            ResponseFormat::Cbor
+        );
+        // The highest weight wins, wherever it is listed
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/msgpack;q=0.1, application/json")),
+            ResponseFormat::Json
+        );
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/json;q=0.5, application/cbor;q=0.8")),
+            ResponseFormat::Cbor
+        );
+        // A wildcard stands for JSON
+        assert_eq!(
+            ResponseFormat::encoding(&accept("application/msgpack;q=0.5, */*")),
+            ResponseFormat::Json
+        );
+    }
+
+    #[test]
+    fn test_render_markers_geojson() {
+        let markers = vec![Marker {
+            id: 7,
+            uuid: "550e8400-e29b-41d4-a716-446655440000".to_string(),
+            ts_epoch_ms: 1705665600000,
+            lat: 59.91,
+            lon: 10.75,
+            icon_id: "ship".to_string(),
+            label: None,
+        }];
+        let response = serde_json::json!({"max_id": 7, "markers": markers});
+        let rendered = ResponseFormat::GeoJson.render_markers(&response, "markers", &markers);
+        assert_eq!(rendered.status(), StatusCode::OK);
+        assert_eq!(
+            rendered.headers()[header::CONTENT_TYPE],
+            geojson::CONTENT_TYPE
+        );
+
+        // Only objects can carry the collection's foreign members
+        let rendered = ResponseFormat::GeoJson.render_markers(&markers, "markers", &markers);
+        assert_eq!(rendered.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_render_binary_headers() {
+        let response = ResponseFormat::MsgPack.render(&serde_json::json!({"max_id": 7}));
+        assert_eq!(response.headers()[header::CONTENT_TYPE], MSGPACK);
+        assert_eq!(response.headers()[header::VARY], "accept");
+    }
+}
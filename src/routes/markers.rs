@@ -1,29 +1,36 @@
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 
 use crate::db;
-use crate::models::{ApiError, CreateMarkerRequest, CreateMarkerResponse};
+use crate::models::{
+    ApiError, BatchCreateRequest, BatchCreateResponse, BatchItemResult, CreateMarkerRequest,
+    CreateMarkerResponse, Marker, ValidationError,
+};
+use crate::routes::format::{Encoded, ResponseFormat};
 use crate::state::AppState;
 
-/// POST /markers - Create a new marker (idempotent).
-pub async fn create_marker(
-    State(state): State<AppState>,
-    Json(req): Json<CreateMarkerRequest>,
-) -> Response {
+/// Error from storing a marker.
+#[derive(Debug)]
+pub enum CreateMarkerError {
+    Invalid(ValidationError),
+    Database(sqlx::Error),
+}
+
+/// Validate and store a marker, notifying log subscribers if it was newly created.
+/// Returns (marker, created) like `db::insert_marker`.
+pub async fn store_marker(
+    state: &AppState,
+    req: &CreateMarkerRequest,
+) -> Result<(Marker, bool), CreateMarkerError> {
     // Validate request including icon_id against available icons
-    if let Err(e) = req.validate_with_icons(&state.icon_ids) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ApiError::from_validation_error(&e)),
-        )
-            .into_response();
-    }
+    req.validate_with_icons(&state.icon_ids)
+        .map_err(CreateMarkerError::Invalid)?;
 
-    match db::insert_marker(
+    let (marker, created) = db::insert_marker(
         &state.pool,
         &req.uuid,
         req.lat,
@@ -32,7 +39,70 @@ pub async fn create_marker(
         req.label.as_deref(),
     )
     .await
-    {
+    .map_err(CreateMarkerError::Database)?;
+
+    if created {
+        state.bus.publish(&marker);
+    }
+
+    Ok((marker, created))
+}
+
+/// Validate and store several markers in one transaction, then notify log
+/// subscribers of the newly created ones. Invalid markers are reported and
+/// skipped; a database error rolls back the whole batch.
+pub async fn store_markers(
+    state: &AppState,
+    reqs: &[CreateMarkerRequest],
+) -> Result<Vec<BatchItemResult>, sqlx::Error> {
+    let mut tx = state.pool.begin().await?;
+    let mut results = Vec::with_capacity(reqs.len());
+    for req in reqs {
+        if let Err(e) = req.validate_with_icons(&state.icon_ids) {
+            results.push(BatchItemResult::Error {
+                uuid: req.uuid.clone(),
+                error: ApiError::from_validation_error(&e),
+            });
+            continue;
+        }
+
+        let (marker, created) = db::insert_marker_in(
+            &mut tx,
+            &req.uuid,
+            req.lat,
+            req.lon,
+            &req.icon_id,
+            req.label.as_deref(),
+        )
+        .await?;
+        results.push(if created {
+            BatchItemResult::Created { marker }
+        } else {
+            BatchItemResult::Exists { marker }
+        });
+    }
+    tx.commit().await?;
+
+    // Publish only once committed, in log order
+    for result in &results {
+        if let BatchItemResult::Created { marker } = result {
+            state.bus.publish(marker);
+        }
+    }
+
+    Ok(results)
+}
+
+/// POST /markers - Create a new marker (idempotent).
+///
+/// The body may be JSON, MessagePack or CBOR (by `Content-Type`), and the
+/// response is encoded per `Accept`.
+pub async fn create_marker(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Encoded(req): Encoded<CreateMarkerRequest>,
+) -> Response {
+    match store_marker(&state, &req).await {
         Ok((marker, created)) => {
             let status_code = if created {
                 StatusCode::CREATED
@@ -43,9 +113,19 @@ pub async fn create_marker(
                 status: if created { "created" } else { "exists" },
                 marker,
             };
-            (status_code, Json(response)).into_response()
+            // Keep the status of an encoding failure
+            let mut response = ResponseFormat::encoding(&headers).render(&response);
+            if response.status().is_success() {
+                *response.status_mut() = status_code;
+            }
+            response
         }
-        Err(e) => {
+        Err(CreateMarkerError::Invalid(e)) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response(),
+        Err(CreateMarkerError::Database(e)) => {
             tracing::error!("Failed to create marker: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -55,3 +135,44 @@ pub async fn create_marker(
         }
     }
 }
+
+/// POST /markers/batch - Create up to `MAX_BATCH_MARKERS` markers in one request
+/// (idempotent per uuid).
+///
+/// Results follow the request order, each `created`, `exists` or `error`.
+/// Accepts and returns the same encodings as `POST /markers`.
+pub async fn create_markers_batch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Encoded(req): Encoded<BatchCreateRequest>,
+) -> Response {
+    if let Err(e) = req.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    match store_markers(&state, &req.markers).await {
+        Ok(results) => {
+            let count =
+                |status: fn(&BatchItemResult) -> bool| results.iter().filter(|r| status(r)).count();
+            let response = BatchCreateResponse {
+                created: count(|r| matches!(r, BatchItemResult::Created { .. })),
+                exists: count(|r| matches!(r, BatchItemResult::Exists { .. })),
+                errors: count(|r| matches!(r, BatchItemResult::Error { .. })),
+                results,
+            };
+            ResponseFormat::encoding(&headers).render(&response)
+        }
+        Err(e) => {
+            tracing::error!("Failed to create markers: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
@@ -1,5 +1,11 @@
 pub mod api;
+pub mod cache;
+pub mod export;
+pub mod format;
 pub mod markers;
+pub mod stream;
+pub mod tiles;
+pub mod ws;
 
 use axum::{
     http::StatusCode,
@@ -16,11 +22,30 @@ pub fn create_router(state: AppState) -> Router {
         .route("/", get(index))
         // Marker creation (append-only, no update/delete)
         .route("/markers", post(markers::create_marker))
+        .route("/markers/batch", post(markers::create_markers_batch))
         // API endpoints
         .route("/api/markers", get(api::get_markers))
+        .route("/api/markers/near", get(api::get_markers_near))
+        .route("/api/markers/lookup", post(api::lookup_markers))
+        .route("/api/markers/{uuid}", get(api::get_marker))
         .route("/api/markers_at", get(api::get_markers_at))
+        .route("/api/markers_range", get(api::get_markers_range))
+        .route("/api/clusters", get(api::get_clusters))
+        .route("/api/histogram", get(api::get_histogram))
+        .route("/api/heatmap", get(api::get_heatmap))
         .route("/api/log", get(api::get_log))
+        .route("/api/log/stream", get(stream::get_log_stream))
+        .route("/api/log.ndjson", get(export::export_log_ndjson))
+        .route("/api/search", get(api::search))
         .route("/api/icons", get(api::get_icons))
+        // File exports
+        .route("/api/export.csv", get(export::export_csv))
+        .route("/api/export.kml", get(export::export_kml))
+        .route("/api/export.gpx", get(export::export_gpx))
+        // Vector tiles; {y} carries the .mvt extension
+        .route("/tiles/{z}/{x}/{y}", get(tiles::get_tile))
+        // WebSocket: create markers and follow the log on one connection
+        .route("/ws", get(ws::ws_handler))
         // Health check
         .route("/health", get(health))
         .with_state(state)
@@ -93,6 +118,7 @@ npm run dev</pre>
         <li><a href="/api/markers">/api/markers</a> - Get markers (last 24h)</li>
         <li><a href="/api/icons">/api/icons</a> - Get available icons</li>
         <li><a href="/api/log">/api/log</a> - Get log entries</li>
+        <li><a href="/api/log/stream">/api/log/stream</a> - Stream log entries (SSE)</li>
         <li><a href="/health">/health</a> - Health check</li>
     </ul>
 </body>
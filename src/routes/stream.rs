@@ -0,0 +1,84 @@
+use std::convert::Infallible;
+
+use futures_util::{Stream, StreamExt};
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+
+use crate::models::{ApiError, LogStreamQuery, ValidationError};
+use crate::state::AppState;
+
+/// GET /api/log/stream?after_id=... - Push log entries as Server-Sent Events.
+///
+/// Each event carries the log `id` as its SSE id, so a reconnecting client
+/// resumes via `Last-Event-ID` exactly like paging with `after_id`.
+pub async fn get_log_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<LogStreamQuery>,
+) -> Response {
+    // Validate query parameters
+    if let Err(e) = query.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    // Last-Event-ID (sent by reconnecting clients) takes precedence over after_id
+    let after_id = match headers.get("last-event-id") {
+        Some(value) => match value.to_str().ok().and_then(|v| v.parse::<i64>().ok()) {
+            Some(id) if id >= 0 => id,
+            _ => {
+                let e = ValidationError::InvalidLastEventId(
+                    String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                );
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError::from_validation_error(&e)),
+                )
+                    .into_response();
+            }
+        },
+        None => query.after_id,
+    };
+
+    Sse::new(log_events(state, after_id))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Stream log entries after `after_id`, then new commits as they happen.
+fn log_events(state: AppState, after_id: i64) -> impl Stream<Item = Result<Event, Infallible>> {
+    async_stream::stream! {
+        let entries = state.follow_log(after_id);
+        futures_util::pin_mut!(entries);
+
+        while let Some(entry) = entries.next().await {
+            let marker = match entry {
+                Ok(marker) => marker,
+                Err(e) => {
+                    // End the stream; the client reconnects with Last-Event-ID
+                    tracing::error!("Failed to stream log: {}", e);
+                    break;
+                }
+            };
+            match Event::default()
+                .id(marker.id.to_string())
+                .event("marker")
+                .json_data(&marker)
+            {
+                Ok(event) => yield Ok(event),
+                Err(e) => tracing::error!("Failed to encode log event: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::db;
+use crate::export::mvt;
+use crate::models::{ApiError, TileCoord, TileQuery};
+use crate::state::AppState;
+
+/// GET /tiles/{z}/{x}/{y}.mvt?window_ms=... - Markers in the visibility window as
+/// a Mapbox Vector Tile, for MapLibre and other 2D map clients.
+///
+/// Tiles hold at most the server's markers page cap: the newest markers in the
+/// tile.
+pub async fn get_tile(
+    State(state): State<AppState>,
+    Path((z, x, y)): Path<(String, String, String)>,
+    Query(query): Query<TileQuery>,
+) -> Response {
+    // Validate path and query parameters
+    let (tile, window_ms) = match TileCoord::parse(&z, &x, &y)
+        .and_then(|tile| Ok((tile, state.window(query.window_ms)?)))
+    {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::from_validation_error(&e)),
+            )
+                .into_response();
+        }
+    };
+
+    let bbox = tile.bbox();
+    match db::get_latest_markers_in_window(&state.pool, window_ms, &bbox, state.max_markers_page)
+        .await
+    {
+        Ok((markers, _)) => (
+            [(header::CONTENT_TYPE, mvt::CONTENT_TYPE)],
+            mvt::encode(tile, &markers),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to get tile markers: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new(format!("Database error: {}", e))),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::StreamExt;
+
+use crate::models::{
+    ApiError, CreateMarkerRequest, CreateMarkerResponse, LogStreamQuery, WsServerMessage,
+};
+use crate::routes::markers::{store_marker, CreateMarkerError};
+use crate::state::AppState;
+
+/// Entries read ahead of a slow WebSocket client before the follower waits.
+const FOLLOW_BUFFER: usize = 64;
+
+/// GET /ws?after_id=... - WebSocket for creating markers and following the log.
+///
+/// Text frames from the client are `CreateMarkerRequest` JSON and are answered
+/// with an `ack` or `error` message. Every log entry with `id > after_id` is
+/// sent as an `entry` message, followed by new entries as they are committed.
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(query): Query<LogStreamQuery>,
+) -> Response {
+    // Validate query parameters
+    if let Err(e) = query.validate() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::from_validation_error(&e)),
+        )
+            .into_response();
+    }
+
+    ws.on_upgrade(move |socket| handle_socket(socket, state, query.after_id))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState, after_id: i64) {
+    // Follow the log in its own task, so a log read in flight is never
+    // cancelled (and its connection held) while a create is handled
+    let (tx, mut entries) = tokio::sync::mpsc::channel(FOLLOW_BUFFER);
+    let follower = state.follow_log(after_id);
+    let pump = tokio::spawn(async move {
+        futures_util::pin_mut!(follower);
+        while let Some(entry) = follower.next().await {
+            if tx.send(entry).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                let reply = match msg {
+                    Some(Ok(Message::Text(text))) => handle_create(&state, text.as_str()).await,
+                    Some(Ok(Message::Binary(_))) => WsServerMessage::Error {
+                        uuid: None,
+                        error: ApiError::new("Binary messages are not supported"),
+                    },
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                if !send_message(&mut socket, &reply).await {
+                    break;
+                }
+            }
+            entry = entries.recv() => {
+                let marker = match entry {
+                    Some(Ok(marker)) => marker,
+                    Some(Err(e)) => {
+                        tracing::error!("Failed to read log for WebSocket: {}", e);
+                        break;
+                    }
+                    None => return,
+                };
+                if !send_message(&mut socket, &WsServerMessage::Entry(marker)).await {
+                    break;
+                }
+            }
+        }
+    }
+    pump.abort();
+}
+
+/// Parse and store a create request, returning the reply for the client.
+async fn handle_create(state: &AppState, text: &str) -> WsServerMessage {
+    let req: CreateMarkerRequest = match serde_json::from_str(text) {
+        Ok(req) => req,
+        Err(e) => {
+            return WsServerMessage::Error {
+                uuid: None,
+                error: ApiError::new(format!("Invalid message: {}", e)),
+            }
+        }
+    };
+
+    match store_marker(state, &req).await {
+        Ok((marker, created)) => WsServerMessage::Ack(CreateMarkerResponse {
+            status: if created { "created" } else { "exists" },
+            marker,
+        }),
+        Err(CreateMarkerError::Invalid(e)) => WsServerMessage::Error {
+            uuid: Some(req.uuid),
+            error: ApiError::from_validation_error(&e),
+        },
+        Err(CreateMarkerError::Database(e)) => {
+            tracing::error!("Failed to create marker: {}", e);
+            WsServerMessage::Error {
+                uuid: Some(req.uuid),
+                error: ApiError::new(format!("Database error: {}", e)),
+            }
+        }
+    }
+}
+
+/// Serialize and send a message. Returns false if the connection is gone.
+async fn send_message(socket: &mut WebSocket, msg: &WsServerMessage) -> bool {
+    let text = match serde_json::to_string(msg) {
+        Ok(text) => text,
+        Err(e) => {
+            tracing::error!("Failed to encode WebSocket message: {}", e);
+            return true;
+        }
+    };
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
@@ -1,8 +1,13 @@
+use futures_util::Stream;
 use sqlx::SqlitePool;
 use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::watch;
 
-use crate::models::Icon;
+use crate::bus::{ChangeBus, LogEvent};
+use crate::config::{DEFAULT_MAX_MARKERS_PAGE, DEFAULT_MAX_WINDOW_MS, DEFAULT_WINDOW_MS};
+use crate::db;
+use crate::models::{Icon, Marker, ValidationError};
 
 /// Application state shared across handlers.
 #[derive(Clone)]
@@ -10,6 +15,16 @@ pub struct AppState {
     pub pool: SqlitePool,
     pub icons: Arc<Vec<Icon>>,
     pub icon_ids: Arc<HashSet<String>>,
+    /// Committed log entries, published by every writer after insert.
+    pub bus: ChangeBus,
+    /// Default visibility window for current markers.
+    pub window_ms: i64,
+    /// Largest per-request `window_ms`.
+    pub max_window_ms: i64,
+    /// Hard cap on markers returned from the visibility window in one response.
+    pub max_markers_page: i64,
+    /// Configured public origin for absolute links, instead of the request's.
+    pub public_url: Option<Arc<str>>,
 }
 
 impl AppState {
@@ -19,6 +34,155 @@ impl AppState {
             pool,
             icons: Arc::new(icons),
             icon_ids: Arc::new(icon_ids),
+            bus: ChangeBus::default(),
+            window_ms: DEFAULT_WINDOW_MS,
+            max_window_ms: DEFAULT_MAX_WINDOW_MS,
+            max_markers_page: DEFAULT_MAX_MARKERS_PAGE,
+            public_url: None,
         }
     }
+
+    /// Use a visibility window other than the 24-hour default.
+    pub fn with_window(mut self, window_ms: i64, max_window_ms: i64) -> Self {
+        self.window_ms = window_ms;
+        self.max_window_ms = max_window_ms;
+        self
+    }
+
+    /// Cap the markers returned per page, 10000 by default.
+    pub fn with_max_markers_page(mut self, max_markers_page: i64) -> Self {
+        self.max_markers_page = max_markers_page;
+        self
+    }
+
+    /// Build absolute links from `public_url` (e.g. `https://fylge.example`)
+    /// rather than from request headers.
+    pub fn with_public_url(mut self, public_url: &str) -> Self {
+        self.public_url = Some(Arc::from(public_url.trim_end_matches('/')));
+        self
+    }
+
+    /// Resolve a request's optional `window_ms` against the configured default and maximum.
+    pub fn window(&self, requested: Option<i64>) -> Result<i64, ValidationError> {
+        match requested {
+            None => Ok(self.window_ms),
+            Some(w) if w > 0 && w <= self.max_window_ms => Ok(w),
+            Some(w) => Err(ValidationError::InvalidWindow(w, self.max_window_ms)),
+        }
+    }
+
+    /// Watch the highest committed log id, used to wake long-polling readers.
+    pub fn watch_log(&self) -> watch::Receiver<i64> {
+        self.bus.watch()
+    }
+
+    /// Follow the log: every entry after `after_id` in id order, then new
+    /// entries as they are committed. Ends only on a database error or when
+    /// the bus is dropped.
+    ///
+    /// The backlog is read from the database; live entries come from the bus,
+    /// so idle followers cost no queries. The database is read again only when
+    /// this follower lagged behind the bus, or an entry arrives with a gap
+    /// before it (writers may publish out of order, and a conflicting insert
+    /// uses up an id).
+    pub fn follow_log(
+        &self,
+        after_id: i64,
+    ) -> impl Stream<Item = Result<Marker, sqlx::Error>> + Send + 'static {
+        let pool = self.pool.clone();
+        // Subscribe before reading the backlog so no commit falls in between
+        let mut sub = self.bus.subscribe();
+
+        async_stream::try_stream! {
+            let mut cursor = after_id;
+            'resync: loop {
+                loop {
+                    let (entries, max_id, has_more) =
+                        db::get_log_after(&pool, cursor, db::MAX_LIMIT, None).await?;
+                    for marker in entries {
+                        yield marker;
+                    }
+                    cursor = max_id;
+                    if !has_more {
+                        break;
+                    }
+                }
+
+                while let Some(event) = sub.recv().await {
+                    match event {
+                        // Already sent with the backlog
+                        LogEvent::Entry(marker) if marker.id <= cursor => {}
+                        LogEvent::Entry(marker) if marker.id == cursor + 1 => {
+                            cursor = marker.id;
+                            yield marker;
+                        }
+                        LogEvent::Entry(_) | LogEvent::Lagged { .. } => continue 'resync,
+                    }
+                }
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    async fn setup_state() -> AppState {
+        let pool = db::init_pool("sqlite::memory:").await.unwrap();
+        db::run_migrations(&pool).await.unwrap();
+        AppState::new(pool, Vec::new())
+    }
+
+    async fn insert(state: &AppState, n: i64) -> Marker {
+        let uuid = format!("550e8400-e29b-41d4-a716-4466554400{:02}", n);
+        let (marker, _) = db::insert_marker(&state.pool, &uuid, 59.91, 10.75, "marker", None)
+            .await
+            .unwrap();
+        marker
+    }
+
+    #[tokio::test]
+    async fn test_follow_log_backlog_then_live() {
+        let state = setup_state().await;
+        insert(&state, 1).await;
+        insert(&state, 2).await;
+
+        let log = state.follow_log(1);
+        futures_util::pin_mut!(log);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 2);
+
+        // Live entries come from the bus, even ones not in the database
+        let mut live = insert(&state, 3).await;
+        state.bus.publish(&live);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 3);
+        live.id = 4;
+        state.bus.publish(&live);
+        assert_eq!(log.next().await.unwrap().unwrap().uuid, live.uuid);
+    }
+
+    #[tokio::test]
+    async fn test_follow_log_resyncs_on_gap() {
+        let state = setup_state().await;
+        let log = state.follow_log(0);
+        futures_util::pin_mut!(log);
+
+        let first = insert(&state, 1).await;
+        state.bus.publish(&first);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 1);
+
+        // Published out of order: 3 arrives first, so the log is re-read
+        let second = insert(&state, 2).await;
+        let third = insert(&state, 3).await;
+        state.bus.publish(&third);
+        state.bus.publish(&second);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 2);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 3);
+
+        let fourth = insert(&state, 4).await;
+        state.bus.publish(&fourth);
+        assert_eq!(log.next().await.unwrap().unwrap().id, 4);
+    }
 }
@@ -5,10 +5,10 @@ use axum::{
 use http_body_util::BodyExt;
 use tower::ServiceExt;
 
-use fylge::{create_router, init_pool, run_migrations, AppState, Icon};
+use fylge::{create_router, current_epoch_ms, init_pool, run_migrations, AppState, Icon, LogEvent};
 
-/// Create a test app with in-memory database.
-async fn create_test_app() -> axum::Router {
+/// Create test state with an in-memory database and the `marker` and `ship` icons.
+async fn create_test_state() -> AppState {
     let pool = init_pool("sqlite::memory:").await.unwrap();
     run_migrations(&pool).await.unwrap();
 
@@ -25,8 +25,17 @@ async fn create_test_app() -> axum::Router {
         },
     ];
 
-    let state = AppState::new(pool, icons);
-    create_router(state)
+    AppState::new(pool, icons)
+}
+
+/// Create a test app, letting `configure` adjust the state first.
+async fn create_test_app_with(configure: impl FnOnce(AppState) -> AppState) -> axum::Router {
+    create_router(configure(create_test_state().await))
+}
+
+/// Create a test app with in-memory database.
+async fn create_test_app() -> axum::Router {
+    create_test_app_with(|state| state).await
 }
 
 /// Helper to get response body as string.
@@ -112,9 +121,82 @@ async fn test_get_markers_empty() {
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
 
     assert_eq!(json["window_hours"], 24);
+    assert_eq!(json["window_ms"], 86_400_000);
     assert!(json["markers"].is_array());
     assert_eq!(json["markers"].as_array().unwrap().len(), 0);
     assert_eq!(json["max_id"], 0);
+    assert_eq!(json["has_more"], false);
+    assert!(json["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn test_get_markers_pagination_capped() {
+    let app = create_test_app_with(|state| state.with_max_markers_page(2)).await;
+
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    // A larger limit is clamped to the cap
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers?limit=1000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["limit"], 2);
+    assert_eq!(json["markers"].as_array().unwrap().len(), 2);
+    assert_eq!(json["has_more"], true);
+    assert_eq!(json["max_id"], 3);
+
+    let cursor = json["next_cursor"].as_str().unwrap();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/markers?cursor={}", cursor))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0]["uuid"], "550e8400-e29b-41d4-a716-446655440003");
+    assert_eq!(json["has_more"], false);
+    assert!(json["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn test_get_markers_invalid_limit() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers?limit=0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "limit");
+    assert!(!json["error"].as_str().unwrap().contains("1000"));
 }
 
 #[tokio::test]
@@ -427,90 +509,177 @@ async fn test_create_marker_unknown_field_rejected() {
     assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 }
 
-// ============================================================================
-// Log endpoint tests
-// ============================================================================
-
 #[tokio::test]
-async fn test_get_log_empty() {
+async fn test_get_markers_bbox_filter() {
     let app = create_test_app().await;
 
+    for (suffix, lat, lon) in [(1, 59.91, 10.75), (2, 40.71, -74.01)] {
+        let _ = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/markers")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(format!(
+                        r#"{{
+                            "uuid": "550e8400-e29b-41d4-a716-44665544000{}",
+                            "lat": {},
+                            "lon": {},
+                            "icon_id": "marker"
+                        }}"#,
+                        suffix, lat, lon
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    for uri in [
+        "/api/markers?bbox=4.5,57.9,31.2,71.2",
+        "/api/log?bbox=4.5,57.9,31.2,71.2",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let list = if uri.starts_with("/api/log") {
+            &json["entries"]
+        } else {
+            &json["markers"]
+        };
+        assert_eq!(list.as_array().unwrap().len(), 1, "{}", uri);
+        assert_eq!(list[0]["lat"], 59.91);
+    }
+}
+
+#[tokio::test]
+async fn test_get_markers_bbox_within_window() {
+    let state = create_test_state().await;
+    let pool = state.pool.clone();
+    let app = create_router(state);
+
+    // Logged in the same area two days ago, outside the default window
+    sqlx::query(
+        "INSERT INTO marker_log (uuid, ts_epoch_ms, lat, lon, icon_id) \
+         VALUES ('550e8400-e29b-41d4-a716-446655440009', ?, 59.91, 10.75, 'marker')",
+    )
+    .bind(current_epoch_ms() - 2 * 24 * 60 * 60 * 1000)
+    .execute(&pool)
+    .await
+    .unwrap();
+    create_marker_with_suffix(&app, 1).await;
+
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri("/api/log")
+                .uri("/api/markers?bbox=4.5,57.9,31.2,71.2")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0]["uuid"], "550e8400-e29b-41d4-a716-446655440001");
 
-    assert_eq!(json["after_id"], 0);
-    assert_eq!(json["limit"], 100);
-    assert_eq!(json["max_id"], 0);
-    assert_eq!(json["has_more"], false);
-    assert!(json["entries"].is_array());
-    assert_eq!(json["entries"].as_array().unwrap().len(), 0);
-}
-
-#[tokio::test]
-async fn test_get_log_with_entries() {
-    let app = create_test_app().await;
-
-    // Create a marker first
-    let _ = app
-        .clone()
+    // Widening the window brings the older marker back
+    let response = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/markers")
-                .header("Content-Type", "application/json")
-                .body(Body::from(
-                    r#"{
-                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
-                        "lat": 59.91,
-                        "lon": 10.75,
-                        "icon_id": "marker",
-                        "label": "Oslo"
-                    }"#,
-                ))
+                .uri("/api/markers?bbox=4.5,57.9,31.2,71.2&window_ms=259200000")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["markers"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_get_markers_invalid_bbox() {
+    let app = create_test_app().await;
+
+    for uri in [
+        "/api/markers?bbox=1,2,3",
+        "/api/markers_at?at=1705665600000&bbox=0,10,10,-10",
+        "/api/log?bbox=a,b,c,d",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["field"], "bbox");
+    }
+}
+
+#[tokio::test]
+async fn test_get_markers_window_ms() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
 
-    // Get log
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri("/api/log?after_id=0")
+                .uri("/api/markers?window_ms=7200000")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["window_hours"], 2);
+    assert_eq!(json["window_ms"], 7_200_000);
+    assert_eq!(json["markers"].as_array().unwrap().len(), 1);
+
+    // Beyond the 7-day default maximum
+    for uri in [
+        "/api/markers?window_ms=604800001",
+        "/api/markers_at?at=1705665600000&window_ms=0",
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
 
-    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
-    assert_eq!(json["max_id"], 1);
-    assert_eq!(json["has_more"], false);
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["field"], "window_ms");
+    }
 }
 
 #[tokio::test]
-async fn test_get_log_pagination() {
+async fn test_get_clusters() {
     let app = create_test_app().await;
 
-    // Create 3 markers
-    for i in 1..=3 {
+    // Two markers in Oslo, one in Bergen
+    for (suffix, lat, lon) in [(1, 59.91, 10.75), (2, 59.92, 10.76), (3, 60.39, 5.32)] {
         let _ = app
             .clone()
             .oneshot(
@@ -521,11 +690,11 @@ async fn test_get_log_pagination() {
                     .body(Body::from(format!(
                         r#"{{
                             "uuid": "550e8400-e29b-41d4-a716-44665544000{}",
-                            "lat": 59.91,
-                            "lon": 10.75,
+                            "lat": {},
+                            "lon": {},
                             "icon_id": "marker"
                         }}"#,
-                        i
+                        suffix, lat, lon
                     )))
                     .unwrap(),
             )
@@ -533,141 +702,1803 @@ async fn test_get_log_pagination() {
             .unwrap();
     }
 
-    // Get first page (limit 2)
     let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .uri("/api/log?after_id=0&limit=2")
+                .uri("/api/clusters?zoom=4")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-
-    assert_eq!(json["entries"].as_array().unwrap().len(), 2);
-    assert_eq!(json["has_more"], true);
-    assert_eq!(json["max_id"], 2);
-
-    // Get second page
+    assert_eq!(json["cell_deg"], 2.8125);
+    assert!(json["markers"].as_array().unwrap().is_empty());
+    let clusters = json["clusters"].as_array().unwrap();
+    assert_eq!(clusters.len(), 2);
+    assert_eq!(clusters[0]["count"], 2);
+    assert_eq!(clusters[0]["icons"]["marker"], 2);
+    assert_eq!(clusters[1]["count"], 1);
+
+    // High zoom returns the markers themselves
     let response = app
         .oneshot(
             Request::builder()
-                .uri("/api/log?after_id=2&limit=2")
+                .uri("/api/clusters?zoom=14&bbox=10,59,11,60")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-
-    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+    assert!(json["clusters"].as_array().unwrap().is_empty());
+    assert_eq!(json["markers"].as_array().unwrap().len(), 2);
     assert_eq!(json["has_more"], false);
-    assert_eq!(json["max_id"], 3);
 }
 
-// ============================================================================
-// Timestamp format tests
-// ============================================================================
-
 #[tokio::test]
-async fn test_marker_has_epoch_ms_timestamp() {
-    let app = create_test_app().await;
+async fn test_get_clusters_expanded_newest_first() {
+    let app = create_test_app_with(|state| state.with_max_markers_page(2)).await;
 
-    // Create a marker
-    let _ = app
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    // Markers at high zoom need a bbox
+    let response = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/markers")
-                .header("Content-Type", "application/json")
-                .body(Body::from(
-                    r#"{
-                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
-                        "lat": 59.91,
-                        "lon": 10.75,
-                        "icon_id": "marker"
-                    }"#,
-                ))
+                .uri("/api/clusters?zoom=14")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
-    // Get markers and verify timestamp format
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "bbox");
+
+    // Over the page cap, the newest markers are kept
     let response = app
         .oneshot(
             Request::builder()
-                .uri("/api/markers")
+                .uri("/api/clusters?zoom=14&bbox=10,59,11,60")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-
-    // Verify server_time_ms is a number (epoch milliseconds)
-    assert!(json["server_time_ms"].is_i64());
-    let server_time = json["server_time_ms"].as_i64().unwrap();
-    assert!(server_time > 1700000000000); // After Nov 2023
-
-    // Verify marker has ts_epoch_ms (not ts string)
-    let marker = &json["markers"][0];
-    assert!(marker["ts_epoch_ms"].is_i64());
-    let ts = marker["ts_epoch_ms"].as_i64().unwrap();
-    assert!(ts > 1700000000000); // After Nov 2023
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[1]["uuid"], "550e8400-e29b-41d4-a716-446655440003");
+    assert_eq!(json["has_more"], true);
 }
 
 #[tokio::test]
-async fn test_log_has_epoch_ms_timestamp() {
+async fn test_get_clusters_invalid_zoom() {
     let app = create_test_app().await;
 
-    // Create a marker
-    let _ = app
-        .clone()
+    let response = app
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/markers")
-                .header("Content-Type", "application/json")
-                .body(Body::from(
-                    r#"{
-                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
-                        "lat": 59.91,
-                        "lon": 10.75,
-                        "icon_id": "marker"
-                    }"#,
-                ))
+                .uri("/api/clusters?zoom=21")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    // Get log and verify timestamp format
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "zoom");
+}
+
+#[tokio::test]
+async fn test_get_histogram() {
+    let app = create_test_app().await;
+
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    // One bucket spanning all time holds every marker
     let response = app
+        .clone()
         .oneshot(
             Request::builder()
-                .uri("/api/log")
+                .uri(
+                    "/api/histogram?from=0&to=9999999999999&bucket_ms=9999999999999&icon_id=marker",
+                )
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
 
     let body = body_string(response.into_body()).await;
     let json: serde_json::Value = serde_json::from_str(&body).unwrap();
-
-    // Verify server_time_ms is a number (epoch milliseconds)
-    assert!(json["server_time_ms"].is_i64());
-    let server_time = json["server_time_ms"].as_i64().unwrap();
+    assert_eq!(json["total"], 3);
+    assert_eq!(json["icon_id"], "marker");
+    let buckets = json["buckets"].as_array().unwrap();
+    assert_eq!(buckets.len(), 2);
+    assert_eq!(buckets[0]["start_ms"], 0);
+    assert_eq!(buckets[0]["count"], 3);
+    assert_eq!(buckets[1]["count"], 0);
+
+    // Too many buckets
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/histogram?from=0&to=1705665600000&bucket_ms=1000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "bucket_ms");
+}
+
+#[tokio::test]
+async fn test_get_heatmap() {
+    let app = create_test_app().await;
+
+    for suffix in 1..=2 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/heatmap?cell_deg=5&half_life_ms=3600000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["cell_deg"], 5.0);
+    assert_eq!(json["half_life_ms"], 3_600_000);
+    let cells = json["cells"].as_array().unwrap();
+    assert_eq!(cells.len(), 1);
+    assert_eq!(cells[0]["count"], 2);
+    assert_eq!(json["max_weight"], cells[0]["weight"]);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/heatmap?cell_deg=0.001")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "cell_deg");
+}
+
+#[tokio::test]
+async fn test_get_marker_by_uuid() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/550e8400-e29b-41d4-a716-446655440001")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["uuid"], "550e8400-e29b-41d4-a716-446655440001");
+    assert_eq!(json["id"], 1);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/550e8400-e29b-41d4-a716-446655440099")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/not-a-uuid")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "uuid");
+}
+
+#[tokio::test]
+async fn test_lookup_markers() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+    create_marker_with_suffix(&app, 2).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/markers/lookup")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"uuids": [
+                        "550e8400-e29b-41d4-a716-446655440002",
+                        "550e8400-e29b-41d4-a716-446655440099",
+                        "550e8400-e29b-41d4-a716-446655440001"
+                    ]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["status"], "found");
+    assert_eq!(results[0]["marker"]["id"], 2);
+    assert_eq!(results[1]["uuid"], "550e8400-e29b-41d4-a716-446655440099");
+    assert_eq!(results[1]["status"], "not_found");
+    assert!(results[1].get("marker").is_none());
+    assert_eq!(results[2]["marker"]["id"], 1);
+}
+
+#[tokio::test]
+async fn test_get_markers_near() {
+    let app = create_test_app().await;
+
+    // Oslo, Drammen (~35 km), Bergen (~305 km)
+    for (suffix, lat, lon) in [(1, 59.91, 10.75), (2, 59.74, 10.20), (3, 60.39, 5.32)] {
+        let _ = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/markers")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(format!(
+                        r#"{{
+                            "uuid": "550e8400-e29b-41d4-a716-44665544000{}",
+                            "lat": {},
+                            "lon": {},
+                            "icon_id": "marker"
+                        }}"#,
+                        suffix, lat, lon
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Search from Drammen: Drammen first, then Oslo; Bergen out of range
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/near?lat=59.74&lon=10.20&radius_km=50")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 2);
+    assert_eq!(markers[0]["lat"], 59.74);
+    assert_eq!(markers[0]["distance_km"], 0.0);
+    assert_eq!(markers[1]["lat"], 59.91);
+
+    let distance = markers[1]["distance_km"].as_f64().unwrap();
+    assert!(distance > 30.0 && distance < 40.0, "{}", distance);
+    // Oslo is north-east of Drammen
+    let bearing = markers[1]["bearing_deg"].as_f64().unwrap();
+    assert!(bearing > 0.0 && bearing < 90.0, "{}", bearing);
+}
+
+#[tokio::test]
+async fn test_get_markers_near_beyond_page_cap() {
+    let app = create_test_app_with(|state| state.with_max_markers_page(2)).await;
+
+    // Two in Oslo, then the newest one in Drammen
+    create_marker_with_suffix(&app, 1).await;
+    create_marker_with_suffix(&app, 2).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440003",
+                        "lat": 59.74,
+                        "lon": 10.20,
+                        "icon_id": "marker"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    // More markers in range than the page cap: the nearest is still found
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/near?lat=59.74&lon=10.20&radius_km=50&limit=10")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 3);
+    assert_eq!(markers[0]["uuid"], "550e8400-e29b-41d4-a716-446655440003");
+    assert_eq!(markers[0]["distance_km"], 0.0);
+
+    // Only the nearest are kept when the limit is smaller
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/near?lat=59.74&lon=10.20&radius_km=50&limit=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let markers = json["markers"].as_array().unwrap();
+    assert_eq!(markers.len(), 1);
+    assert_eq!(markers[0]["uuid"], "550e8400-e29b-41d4-a716-446655440003");
+}
+
+#[tokio::test]
+async fn test_get_markers_near_invalid_radius() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers/near?lat=59.74&lon=10.20&radius_km=-5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "radius_km");
+}
+
+#[tokio::test]
+async fn test_get_markers_range_pagination() {
+    let app = create_test_app().await;
+
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    let mut uuids = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut uri = "/api/markers_range?from=0&to=9999999999999&limit=2".to_string();
+        if let Some(cursor) = &cursor {
+            uri.push_str(&format!("&cursor={}", cursor));
+        }
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        for marker in json["markers"].as_array().unwrap() {
+            uuids.push(marker["uuid"].as_str().unwrap().to_string());
+        }
+        if !json["has_more"].as_bool().unwrap() {
+            assert!(json["next_cursor"].is_null());
+            break;
+        }
+        cursor = Some(json["next_cursor"].as_str().unwrap().to_string());
+    }
+
+    assert_eq!(
+        uuids,
+        vec![
+            "550e8400-e29b-41d4-a716-446655440001",
+            "550e8400-e29b-41d4-a716-446655440002",
+            "550e8400-e29b-41d4-a716-446655440003",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_get_markers_range_invalid_cursor() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers_range?from=0&to=1705665600000&cursor=nope")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "cursor");
+}
+
+#[tokio::test]
+async fn test_get_markers_etag() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let get = |etag: Option<String>| {
+        let mut request = Request::builder().uri("/api/markers");
+        if let Some(etag) = etag {
+            request = request.header("If-None-Match", etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = get(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["cache-control"], "public, no-cache");
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    let response = get(Some(etag.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    assert!(body_string(response.into_body()).await.is_empty());
+
+    // A new marker changes the tag
+    create_marker_with_suffix(&app, 2).await;
+    let response = get(Some(etag.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+}
+
+#[tokio::test]
+async fn test_get_markers_at_past_window_immutable() {
+    let app = create_test_app().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers_at?at=1705665600000&window_ms=86400000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["cache-control"],
+        "public, max-age=31536000, immutable"
+    );
+
+    // The default window follows configuration, so it may change
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers_at?at=1705665600000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("cache-control").is_none());
+
+    // A window ending now may still change
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/api/markers_at?at={}&window_ms=86400000", now))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("cache-control").is_none());
+}
+
+// ============================================================================
+// Log endpoint tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_get_log_empty() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["after_id"], 0);
+    assert_eq!(json["limit"], 100);
+    assert_eq!(json["max_id"], 0);
+    assert_eq!(json["has_more"], false);
+    assert!(json["entries"].is_array());
+    assert_eq!(json["entries"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_get_log_with_entries() {
+    let app = create_test_app().await;
+
+    // Create a marker first
+    let _ = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
+                        "lat": 59.91,
+                        "lon": 10.75,
+                        "icon_id": "marker",
+                        "label": "Oslo"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Get log
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(json["max_id"], 1);
+    assert_eq!(json["has_more"], false);
+}
+
+#[tokio::test]
+async fn test_get_log_pagination() {
+    let app = create_test_app().await;
+
+    // Create 3 markers
+    for i in 1..=3 {
+        let _ = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/markers")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(format!(
+                        r#"{{
+                            "uuid": "550e8400-e29b-41d4-a716-44665544000{}",
+                            "lat": 59.91,
+                            "lon": 10.75,
+                            "icon_id": "marker"
+                        }}"#,
+                        i
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    // Get first page (limit 2)
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&limit=2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["entries"].as_array().unwrap().len(), 2);
+    assert_eq!(json["has_more"], true);
+    assert_eq!(json["max_id"], 2);
+
+    // Get second page
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=2&limit=2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(json["has_more"], false);
+    assert_eq!(json["max_id"], 3);
+}
+
+#[tokio::test]
+async fn test_get_log_long_poll_wakes_on_insert() {
+    let app = create_test_app().await;
+
+    let poll = tokio::spawn(
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&wait_ms=10000")
+                .body(Body::empty())
+                .unwrap(),
+        ),
+    );
+
+    // Give the long-poll a moment to start waiting
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), poll)
+        .await
+        .expect("long-poll was not woken by insert")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(json["max_id"], 1);
+}
+
+#[tokio::test]
+async fn test_get_log_long_poll_ignores_inserts_outside_bbox() {
+    let app = create_test_app().await;
+
+    // Waiting for Bergen
+    let poll = tokio::spawn(
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&wait_ms=10000&bbox=5,60,6,61")
+                .body(Body::empty())
+                .unwrap(),
+        ),
+    );
+
+    // An insert in Oslo does not end the wait
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    create_marker_with_suffix(&app, 1).await;
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(!poll.is_finished());
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440002",
+                        "lat": 60.39,
+                        "lon": 5.32,
+                        "icon_id": "marker"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = tokio::time::timeout(std::time::Duration::from_secs(5), poll)
+        .await
+        .expect("long-poll was not woken by insert in bbox")
+        .unwrap()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let entries = json["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["lat"], 60.39);
+}
+
+#[tokio::test]
+async fn test_get_log_long_poll_times_out_empty() {
+    let app = create_test_app().await;
+
+    let started = std::time::Instant::now();
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&wait_ms=100")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["entries"].as_array().unwrap().len(), 0);
+    assert_eq!(json["max_id"], 0);
+}
+
+#[tokio::test]
+async fn test_get_log_before_id() {
+    let app = create_test_app().await;
+
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    let mut ids = Vec::new();
+    let mut before_id = 0;
+    loop {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/log?before_id={}&limit=2", before_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["before_id"], before_id);
+        assert_eq!(
+            json["max_id"],
+            if before_id == 0 { 3 } else { before_id - 1 }
+        );
+        for entry in json["entries"].as_array().unwrap() {
+            ids.push(entry["id"].as_i64().unwrap());
+        }
+        if !json["has_more"].as_bool().unwrap() {
+            break;
+        }
+        before_id = json["min_id"].as_i64().unwrap();
+    }
+
+    assert_eq!(ids, vec![3, 2, 1]);
+
+    // Paging backwards cannot be combined with after_id
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?before_id=5&after_id=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "before_id");
+}
+
+#[tokio::test]
+async fn test_get_log_etag() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["etag"], r#"W/"1""#);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=1")
+                .header("If-None-Match", r#"W/"1""#)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // A long-poll with a current tag that times out is still not modified
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=1&wait_ms=50")
+                .header("If-None-Match", r#"W/"1""#)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_get_log_invalid_wait_ms() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?wait_ms=60000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    assert!(body.contains("wait_ms"));
+}
+
+// ============================================================================
+// GeoJSON output tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_get_markers_geojson() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers?format=geojson")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/geo+json");
+    assert_eq!(response.headers()["vary"], "accept");
+    assert!(response.headers()["etag"]
+        .to_str()
+        .unwrap()
+        .ends_with(r#"-geojson""#));
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["type"], "FeatureCollection");
+    assert_eq!(json["max_id"], 1);
+    assert!(json.get("markers").is_none());
+
+    let feature = &json["features"][0];
+    assert_eq!(feature["type"], "Feature");
+    assert_eq!(feature["geometry"]["type"], "Point");
+    assert_eq!(feature["geometry"]["coordinates"][0], 10.75);
+    assert_eq!(feature["geometry"]["coordinates"][1], 59.91);
+    assert_eq!(
+        feature["properties"]["uuid"],
+        "550e8400-e29b-41d4-a716-446655440001"
+    );
+    assert_eq!(feature["properties"]["icon_id"], "marker");
+}
+
+#[tokio::test]
+async fn test_get_markers_at_geojson_accept_header() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers_at?at=1705665600000")
+                .header("Accept", "application/geo+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/geo+json");
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["type"], "FeatureCollection");
+    assert_eq!(json["at_epoch_ms"], 1705665600000i64);
+    assert_eq!(json["features"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn test_get_log_geojson() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+    create_marker_with_suffix(&app, 2).await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0")
+                .header("Accept", "application/geo+json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["etag"], r#"W/"2-geojson""#);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["type"], "FeatureCollection");
+    assert_eq!(json["max_id"], 2);
+    let ids: Vec<i64> = json["features"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    // The JSON tag does not validate the GeoJSON representation
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&format=geojson")
+                .header("If-None-Match", r#"W/"2""#)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_invalid_format() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers?format=kml")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "format");
+}
+
+// ============================================================================
+// Batch create tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_create_markers_batch() {
+    let state = create_test_state().await;
+    let mut subscription = state.bus.subscribe();
+    let app = create_router(state);
+
+    create_marker_with_suffix(&app, 1).await;
+    subscription.recv().await.unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers/batch")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"markers": [
+                        {"uuid": "550e8400-e29b-41d4-a716-446655440002", "lat": 60.39, "lon": 5.32, "icon_id": "marker"},
+                        {"uuid": "550e8400-e29b-41d4-a716-446655440001", "lat": 59.91, "lon": 10.75, "icon_id": "marker"},
+                        {"uuid": "550e8400-e29b-41d4-a716-446655440003", "lat": 91.0, "lon": 10.75, "icon_id": "marker"},
+                        {"uuid": "550e8400-e29b-41d4-a716-446655440004", "lat": 63.43, "lon": 10.39, "icon_id": "bicycle"},
+                        {"uuid": "550e8400-e29b-41d4-a716-446655440005", "lat": 69.65, "lon": 18.96, "icon_id": "marker", "label": "Tromsø"}
+                    ]}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["created"], 2);
+    assert_eq!(json["exists"], 1);
+    assert_eq!(json["errors"], 2);
+
+    let results = json["results"].as_array().unwrap();
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|r| r["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        statuses,
+        vec!["created", "exists", "error", "error", "created"]
+    );
+    assert_eq!(results[0]["marker"]["id"], 2);
+    assert_eq!(results[1]["marker"]["id"], 1);
+    assert_eq!(results[2]["uuid"], "550e8400-e29b-41d4-a716-446655440003");
+    assert_eq!(results[2]["field"], "lat");
+    assert_eq!(results[3]["field"], "icon_id");
+    assert_eq!(results[4]["marker"]["label"], "Tromsø");
+
+    // Created markers are published in log order
+    for created in [&results[0], &results[4]] {
+        match subscription.recv().await {
+            Some(LogEvent::Entry(marker)) => assert_eq!(created["marker"]["id"], marker.id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_create_markers_batch_too_many() {
+    let app = create_test_app().await;
+
+    let marker = r#"{"uuid": "550e8400-e29b-41d4-a716-446655440001", "lat": 59.91, "lon": 10.75, "icon_id": "marker"}"#;
+    let body = format!(r#"{{"markers": [{}]}}"#, vec![marker; 1001].join(","));
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers/batch")
+                .header("Content-Type", "application/json")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "markers");
+}
+
+// ============================================================================
+// Binary encoding tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_create_marker_msgpack_body_cbor_response() {
+    let app = create_test_app().await;
+
+    let body = rmp_serde::to_vec_named(&serde_json::json!({
+        "uuid": "550e8400-e29b-41d4-a716-446655440001",
+        "lat": 59.91,
+        "lon": 10.75,
+        "icon_id": "marker",
+        "label": "Oslo"
+    }))
+    .unwrap();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/msgpack")
+                .header("Accept", "application/cbor")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    assert_eq!(response.headers()["content-type"], "application/cbor");
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = ciborium::from_reader(&bytes[..]).unwrap();
+    assert_eq!(json["status"], "created");
+    assert_eq!(json["marker"]["label"], "Oslo");
+
+    // Undecodable bodies are rejected like malformed JSON
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/cbor")
+                .body(Body::from(vec![0xff, 0x00]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_get_markers_msgpack() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let get = |accept: &'static str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri("/api/markers")
+                .header("Accept", accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let response = get("application/msgpack").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/msgpack");
+    assert_eq!(response.headers()["vary"], "accept");
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    assert!(etag.ends_with(r#"-msgpack""#));
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(json["max_id"], 1);
+    assert_eq!(
+        json["markers"][0]["uuid"],
+        "550e8400-e29b-41d4-a716-446655440001"
+    );
+
+    // Smaller than the same response in JSON
+    let response = get("application/json").await.unwrap();
+    let json_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(bytes.len() < json_bytes.len());
+}
+
+#[tokio::test]
+async fn test_get_log_cbor_format_param() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log?after_id=0&format=cbor")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/cbor");
+    assert_eq!(response.headers()["etag"], r#"W/"1-cbor""#);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = ciborium::from_reader(&bytes[..]).unwrap();
+    assert_eq!(json["entries"][0]["id"], 1);
+}
+
+#[tokio::test]
+async fn test_get_icons_msgpack() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/icons")
+                .header("Accept", "application/msgpack")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = rmp_serde::from_slice(&bytes).unwrap();
+    assert_eq!(json["icons"][1]["id"], "ship");
+}
+
+// ============================================================================
+// Export tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_export_csv() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440002",
+                        "lat": 60.39,
+                        "lon": 5.32,
+                        "icon_id": "ship",
+                        "label": "Pier 3, \"north\""
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.csv?from=0&to=9999999999999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+
+    let body = body_string(response.into_body()).await;
+    let lines: Vec<&str> = body.split_terminator("\r\n").collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "id,uuid,ts_epoch_ms,timestamp,lat,lon,icon_id,label"
+    );
+    assert!(lines[1].starts_with("1,550e8400-e29b-41d4-a716-446655440001,"));
+    assert!(lines[2].ends_with(r#",60.39,5.32,ship,"Pier 3, ""north""""#));
+
+    // Icon filter
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.csv?from=0&to=9999999999999&icon_id=ship")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = body_string(response.into_body()).await;
+    assert_eq!(body.split_terminator("\r\n").count(), 2);
+}
+
+#[tokio::test]
+async fn test_export_kml() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.kml?from=0&to=9999999999999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.google-earth.kml+xml"
+    );
+
+    let body = body_string(response.into_body()).await;
+    assert!(body.starts_with("<?xml"));
+    assert!(body
+        .contains("<Style id=\"icon-ship\"><IconStyle><Icon><href>/static/icons/ship.svg</href>"));
+    assert_eq!(body.matches("<Placemark ").count(), 1);
+    assert!(body.contains("<styleUrl>#icon-marker</styleUrl>"));
+    assert!(body.contains("<coordinates>10.75,59.91</coordinates>"));
+    assert!(body.ends_with("</Document>\n</kml>\n"));
+}
+
+#[tokio::test]
+async fn test_export_kml_public_url() {
+    let app = create_test_app_with(|state| state.with_public_url("https://fylge.example/")).await;
+
+    // The configured URL wins over whatever the client claims
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.kml?from=0&to=9999999999999")
+                .header("Host", "evil.example")
+                .header("X-Forwarded-Proto", "http")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response.into_body()).await;
+    assert!(body.contains("<href>https://fylge.example/static/icons/ship.svg</href>"));
+    assert!(!body.contains("evil.example"));
+
+    // Without one, links stay relative rather than trusting the request
+    let app = create_test_app().await;
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("https://evil.example/api/export.kml?from=0&to=9999999999999")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response.into_body()).await;
+    assert!(body.contains("<href>/static/icons/ship.svg</href>"));
+    assert!(!body.contains("evil.example"));
+}
+
+#[tokio::test]
+async fn test_export_gpx() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    // A bbox around Bergen leaves out the Oslo marker
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.gpx?from=0&to=9999999999999&bbox=5,60,6,61")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = body_string(response.into_body()).await;
+    assert_eq!(body.matches("<wpt ").count(), 0);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.gpx?from=0&to=9999999999999&bbox=10,59,11,60")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/gpx+xml");
+
+    let body = body_string(response.into_body()).await;
+    assert!(body.contains("<gpx version=\"1.1\""));
+    assert!(body.contains("<wpt lat=\"59.91\" lon=\"10.75\"><time>"));
+    assert!(body.contains("<sym>marker</sym></wpt>"));
+    assert!(body.ends_with("</gpx>\n"));
+}
+
+#[tokio::test]
+async fn test_export_log_ndjson() {
+    let app = create_test_app().await;
+    for i in 1..=3 {
+        create_marker_with_suffix(&app, i).await;
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log.ndjson?after_id=1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+
+    let body = body_string(response.into_body()).await;
+    assert!(body.ends_with('\n'));
+    let ids: Vec<i64> = body
+        .lines()
+        .map(|line| {
+            let entry: serde_json::Value = serde_json::from_str(line).unwrap();
+            entry["id"].as_i64().unwrap()
+        })
+        .collect();
+    assert_eq!(ids, vec![2, 3]);
+}
+
+#[tokio::test]
+async fn test_export_csv_invalid_range() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/export.csv?from=2000&to=1000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+// ============================================================================
+// Vector tile tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_get_tile() {
+    let app = create_test_app().await;
+    create_marker_with_suffix(&app, 1).await;
+
+    let get = |uri: &'static str| {
+        app.clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+    };
+
+    // Oslo is in 10/542/297
+    let response = get("/tiles/10/542/297.mvt").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/vnd.mapbox-vector-tile"
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let uuid = b"550e8400-e29b-41d4-a716-446655440001";
+    assert!(bytes.windows(uuid.len()).any(|w| w == uuid));
+
+    // A neighbouring tile has the layer but no markers
+    let response = get("/tiles/10/543/297.mvt").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(!bytes.windows(uuid.len()).any(|w| w == uuid));
+}
+
+#[tokio::test]
+async fn test_get_tile_keeps_newest_markers() {
+    let app = create_test_app_with(|state| state.with_max_markers_page(2)).await;
+
+    for suffix in 1..=3 {
+        create_marker_with_suffix(&app, suffix).await;
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/tiles/10/542/297.mvt")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Over the cap, the oldest marker is the one left out
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let contains = |uuid: &[u8]| bytes.windows(uuid.len()).any(|w| w == uuid);
+    assert!(contains(b"550e8400-e29b-41d4-a716-446655440003"));
+    assert!(contains(b"550e8400-e29b-41d4-a716-446655440002"));
+    assert!(!contains(b"550e8400-e29b-41d4-a716-446655440001"));
+}
+
+#[tokio::test]
+async fn test_get_tile_invalid() {
+    let app = create_test_app().await;
+
+    for uri in ["/tiles/1/2/0.mvt", "/tiles/1/0/0.png", "/tiles/x/0/0.mvt"] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        let body = body_string(response.into_body()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["field"], "tile");
+    }
+}
+
+// ============================================================================
+// Search endpoint tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_search_labels() {
+    let app = create_test_app().await;
+
+    for (suffix, label) in [
+        (1, "Oil slick"),
+        (2, "Fishing boat"),
+        (3, "<b>oil</b> drum"),
+    ] {
+        let _ = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/markers")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(format!(
+                        r#"{{
+                            "uuid": "550e8400-e29b-41d4-a716-44665544000{}",
+                            "lat": 59.91,
+                            "lon": 10.75,
+                            "icon_id": "marker",
+                            "label": "{}"
+                        }}"#,
+                        suffix, label
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/search?q=oil&limit=10")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["q"], "oil");
+    assert_eq!(json["has_more"], false);
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+
+    let highlights: Vec<&str> = results
+        .iter()
+        .map(|r| r["highlight"].as_str().unwrap())
+        .collect();
+    assert!(highlights.contains(&"<mark>Oil</mark> slick"));
+    // Label markup is escaped, only the match is marked up
+    assert!(highlights.contains(&"&lt;b&gt;<mark>oil</mark>&lt;/b&gt; drum"));
+    assert!(results[0]["score"].is_f64());
+    assert!(results[0]["uuid"].is_string());
+}
+
+#[tokio::test]
+async fn test_search_invalid_query() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/search?q=%22*")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "q");
+}
+
+// ============================================================================
+// Timestamp format tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_marker_has_epoch_ms_timestamp() {
+    let app = create_test_app().await;
+
+    // Create a marker
+    let _ = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
+                        "lat": 59.91,
+                        "lon": 10.75,
+                        "icon_id": "marker"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Get markers and verify timestamp format
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/markers")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    // Verify server_time_ms is a number (epoch milliseconds)
+    assert!(json["server_time_ms"].is_i64());
+    let server_time = json["server_time_ms"].as_i64().unwrap();
+    assert!(server_time > 1700000000000); // After Nov 2023
+
+    // Verify marker has ts_epoch_ms (not ts string)
+    let marker = &json["markers"][0];
+    assert!(marker["ts_epoch_ms"].is_i64());
+    let ts = marker["ts_epoch_ms"].as_i64().unwrap();
+    assert!(ts > 1700000000000); // After Nov 2023
+}
+
+#[tokio::test]
+async fn test_log_has_epoch_ms_timestamp() {
+    let app = create_test_app().await;
+
+    // Create a marker
+    let _ = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440000",
+                        "lat": 59.91,
+                        "lon": 10.75,
+                        "icon_id": "marker"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Get log and verify timestamp format
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let body = body_string(response.into_body()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    // Verify server_time_ms is a number (epoch milliseconds)
+    assert!(json["server_time_ms"].is_i64());
+    let server_time = json["server_time_ms"].as_i64().unwrap();
     assert!(server_time > 1700000000000); // After Nov 2023
 
     // Verify entry has ts_epoch_ms
@@ -754,3 +2585,322 @@ async fn test_create_and_get_markers() {
     assert!(labels.contains(&"Oslo"));
     assert!(labels.contains(&"Bergen"));
 }
+
+// ============================================================================
+// Log stream (SSE) endpoint tests
+// ============================================================================
+
+/// Helper to read the next SSE frame from a streaming body as a string.
+async fn next_sse_frame(body: &mut Body) -> String {
+    let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.frame())
+        .await
+        .expect("timed out waiting for SSE frame")
+        .unwrap()
+        .unwrap();
+    String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap()
+}
+
+/// Helper to create a marker with the given UUID suffix.
+async fn create_marker_with_suffix(app: &axum::Router, suffix: u32) {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(format!(
+                    r#"{{
+                        "uuid": "550e8400-e29b-41d4-a716-4466554400{:02}",
+                        "lat": 59.91,
+                        "lon": 10.75,
+                        "icon_id": "marker"
+                    }}"#,
+                    suffix
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn test_log_stream_replays_existing_entries() {
+    let app = create_test_app().await;
+
+    create_marker_with_suffix(&app, 1).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log/stream?after_id=0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    let mut body = response.into_body();
+    let frame = next_sse_frame(&mut body).await;
+    assert!(frame.contains("event: marker"));
+    assert!(frame.contains("id: 1"));
+    assert!(frame.contains("550e8400-e29b-41d4-a716-446655440001"));
+}
+
+#[tokio::test]
+async fn test_log_stream_resumes_from_last_event_id() {
+    let app = create_test_app().await;
+
+    create_marker_with_suffix(&app, 1).await;
+    create_marker_with_suffix(&app, 2).await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log/stream?after_id=0")
+                .header("Last-Event-ID", "1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut body = response.into_body();
+    let frame = next_sse_frame(&mut body).await;
+    assert!(frame.contains("id: 2"));
+    assert!(frame.contains("550e8400-e29b-41d4-a716-446655440002"));
+}
+
+#[tokio::test]
+async fn test_log_stream_pushes_new_entries() {
+    let app = create_test_app().await;
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/api/log/stream")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut body = response.into_body();
+    let pending = tokio::spawn(async move { next_sse_frame(&mut body).await });
+
+    create_marker_with_suffix(&app, 1).await;
+
+    let frame = pending.await.unwrap();
+    assert!(frame.contains("id: 1"));
+}
+
+#[tokio::test]
+async fn test_log_stream_invalid_last_event_id() {
+    let app = create_test_app().await;
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/log/stream")
+                .header("Last-Event-ID", "abc")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = body_string(response.into_body()).await;
+    assert!(body.contains("Invalid Last-Event-ID"));
+}
+
+// ============================================================================
+// WebSocket endpoint tests
+// ============================================================================
+
+/// Start the test app on a random local port and return the WebSocket URL.
+async fn spawn_ws_server() -> String {
+    let app = create_test_app().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("ws://{}/ws", addr)
+}
+
+/// Helper to read the next text message from a WebSocket as JSON.
+async fn next_ws_json<S>(ws: &mut S) -> serde_json::Value
+where
+    S: futures_util::Stream<
+            Item = Result<
+                tokio_tungstenite::tungstenite::Message,
+                tokio_tungstenite::tungstenite::Error,
+            >,
+        > + Unpin,
+{
+    use futures_util::StreamExt;
+
+    let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+        .await
+        .expect("timed out waiting for WebSocket message")
+        .unwrap()
+        .unwrap();
+    serde_json::from_str(msg.to_text().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_ws_create_acks_and_pushes_entry() {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let url = spawn_ws_server().await;
+    let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    let request = r#"{
+        "uuid": "550e8400-e29b-41d4-a716-446655440000",
+        "lat": 59.91,
+        "lon": 10.75,
+        "icon_id": "marker",
+        "label": "Oslo"
+    }"#;
+    ws.send(Message::text(request)).await.unwrap();
+
+    let ack = next_ws_json(&mut ws).await;
+    assert_eq!(ack["type"], "ack");
+    assert_eq!(ack["status"], "created");
+    assert_eq!(ack["marker"]["label"], "Oslo");
+
+    let entry = next_ws_json(&mut ws).await;
+    assert_eq!(entry["type"], "entry");
+    assert_eq!(entry["id"], 1);
+    assert_eq!(entry["uuid"], "550e8400-e29b-41d4-a716-446655440000");
+
+    // Same UUID again - acknowledged as existing, no new entry
+    ws.send(Message::text(request)).await.unwrap();
+    let ack = next_ws_json(&mut ws).await;
+    assert_eq!(ack["type"], "ack");
+    assert_eq!(ack["status"], "exists");
+}
+
+#[tokio::test]
+async fn test_ws_receives_entries_from_other_writers() {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let url = spawn_ws_server().await;
+    let (mut writer, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+    let (mut reader, _) = tokio_tungstenite::connect_async(format!("{}?after_id=0", url))
+        .await
+        .unwrap();
+
+    writer
+        .send(Message::text(
+            r#"{
+                "uuid": "550e8400-e29b-41d4-a716-446655440000",
+                "lat": 60.39,
+                "lon": 5.32,
+                "icon_id": "ship"
+            }"#,
+        ))
+        .await
+        .unwrap();
+
+    let entry = next_ws_json(&mut reader).await;
+    assert_eq!(entry["type"], "entry");
+    assert_eq!(entry["icon_id"], "ship");
+}
+
+#[tokio::test]
+async fn test_ws_invalid_request() {
+    use futures_util::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let url = spawn_ws_server().await;
+    let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    ws.send(Message::text(
+        r#"{
+            "uuid": "550e8400-e29b-41d4-a716-446655440000",
+            "lat": 91.0,
+            "lon": 10.75,
+            "icon_id": "marker"
+        }"#,
+    ))
+    .await
+    .unwrap();
+
+    let reply = next_ws_json(&mut ws).await;
+    assert_eq!(reply["type"], "error");
+    assert_eq!(reply["field"], "lat");
+    assert_eq!(reply["uuid"], "550e8400-e29b-41d4-a716-446655440000");
+
+    ws.send(Message::text("not json")).await.unwrap();
+    let reply = next_ws_json(&mut ws).await;
+    assert_eq!(reply["type"], "error");
+    assert!(reply["error"].as_str().unwrap().contains("Invalid message"));
+}
+
+// ============================================================================
+// Change bus tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_change_bus_receives_created_markers() {
+    let state = create_test_state().await;
+    let mut subscription = state.bus.subscribe();
+    let app = create_router(state);
+
+    create_marker_with_suffix(&app, 1).await;
+    // Idempotent repeat must not be published again
+    let _ = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/markers")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{
+                        "uuid": "550e8400-e29b-41d4-a716-446655440001",
+                        "lat": 59.91,
+                        "lon": 10.75,
+                        "icon_id": "marker"
+                    }"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    create_marker_with_suffix(&app, 2).await;
+
+    match subscription.recv().await {
+        Some(LogEvent::Entry(marker)) => {
+            assert_eq!(marker.id, 1);
+            assert_eq!(marker.uuid, "550e8400-e29b-41d4-a716-446655440001");
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    match subscription.recv().await {
+        Some(LogEvent::Entry(marker)) => {
+            assert_eq!(marker.uuid, "550e8400-e29b-41d4-a716-446655440002")
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}